@@ -1,26 +1,148 @@
 
 
 
-pub fn read_patterns_from_file(path: &std::path::Path, pattern_parent_path: &std::path::Path) -> Vec<String> {
+use std::ffi::OsString;
+use crate::pattern_token::{tokenize_pattern, PatternToken};
+
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct IgnoreFileLine {
+
+    // without the line ending, git matches these bytes as they are
+    pub bytes: Vec<u8>,
+
+    // parsed for the pattern syntax, which is ASCII
+    pub text: String,
+
+    // the line had invalid UTF-8 sequences, replaced by U+FFFD in the text only
+    pub lossy: bool
+}
+
+impl IgnoreFileLine {
+
+    pub fn from_text(text: &str) -> Self {
+
+        return Self{bytes: text.as_bytes().to_owned(), text: text.to_owned(), lossy: false};
+    }
+}
+
+#[cfg(test)]
+pub fn read_ignore_file(path: &std::path::Path) -> Vec<IgnoreFileLine> {
+
+    let content = std::fs::read(path).expect("ignore file reading error");
+    return decode_ignore_file(&content);
+}
+
+// reference: https://github.com/git/git/blob/4b79ee4b0cd1130ba8907029cdc5f6a1632aca26/dir.c#L1104
+pub fn decode_ignore_file(mut content: &[u8]) -> Vec<IgnoreFileLine> {
+
+    const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+    if let Some(stripped_content) = content.strip_prefix(UTF8_BOM) {
+
+        content = stripped_content;
+    }
+
+    let mut lines = Vec::new();
+
+    for line in content.split_inclusive(|byte| *byte == b'\n') {
+
+        // git appends a line feed to a last line without one, then strips one carriage return before each line feed
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+        let text = String::from_utf8_lossy(line);
+        let lossy = matches!(text, std::borrow::Cow::Owned(_));
+
+        lines.push(IgnoreFileLine{bytes: line.to_owned(), text: text.into_owned(), lossy});
+    }
+
+    return lines;
+}
+
+#[test]
+fn test_decode_ignore_file() {
+
+    #[track_caller]
+    fn assert_decode(content: &[u8], expected: &[(&str, bool)]) {
+
+        let lines = decode_ignore_file(content).into_iter().map(|line| (line.text, line.lossy)).collect::<Vec<_>>();
+        assert_eq!(lines, expected.iter().map(|(text, lossy)| (text.to_string(), *lossy)).collect::<Vec<_>>());
+    }
+
+    assert_decode(b"", &[]);
+    assert_decode(b"foo\nbar", &[("foo", false), ("bar", false)]);
+    assert_decode(b"foo\nbar\n", &[("foo", false), ("bar", false)]);
+    assert_decode(b"\n\n", &[("", false), ("", false)]);
+
+    // byte order mark
+    assert_decode(b"\xef\xbb\xbffoo\nbar", &[("foo", false), ("bar", false)]);
+    assert_decode(b"foo\n\xef\xbb\xbfbar", &[("foo", false), ("\u{feff}bar", false)]);
+
+    // line endings
+    assert_decode(b"foo\r\nbar\r\n", &[("foo", false), ("bar", false)]);
+    assert_decode(b"foo\r\r\nbar\r", &[("foo\r", false), ("bar", false)]);
+    assert_decode(b"foo\r\r", &[("foo\r", false)]);
+    assert_decode(b"foo\rbar\n", &[("foo\rbar", false)]);
+
+    // invalid UTF-8, the raw bytes are kept
+    assert_decode(b"foo\xff\nbar\n", &[("foo\u{fffd}", true), ("bar", false)]);
+    assert_eq!(decode_ignore_file(b"foo\xff\r\n")[0].bytes, b"foo\xff");
+}
+
+
+// the lines given to "GitignoreBuilder", which only takes UTF-8: a lossy line would match other names than git does
+pub fn matcher_lines(lines: &[IgnoreFileLine]) -> Vec<String> {
+
+    return lines.iter().filter(|line| line.lossy == false).map(|line| line.text.clone()).collect();
+}
+
+
+// the traversal decodes the content it read itself, see "job::load_local_ignore_file"
+#[cfg(test)]
+pub fn read_patterns_from_file(path: &std::path::Path, pattern_parent_path: &std::path::Path) -> Vec<OsString> {
 
     assert!(path.is_file());
 
+    return patterns_from_lines(path, &read_ignore_file(path), pattern_parent_path);
+}
+
+// the ignore file path is only used in the warnings
+pub fn patterns_from_lines(path: &std::path::Path, lines: &[IgnoreFileLine], pattern_parent_path: &std::path::Path) -> Vec<OsString> {
+
     let mut patterns = Vec::new();
     
-    for line in std::fs::read_to_string(path).expect("ignore file reading error").lines() {
+    for (idx, line) in lines.iter().enumerate() {
+
+        warn_lossy_line(path, idx + 1, line);
     
-        if filter_ignore_line(line) == false {
+        if filter_ignore_line(&line.text) == false {
     
             continue;
         }
     
-        let pattern = add_parent_to_ignore_pattern(pattern_parent_path, parse_ignore_pattern(strip_trailing_whitespaces(line)));
-        patterns.push(pattern);
+        patterns.extend(flatten_pattern_line(path, idx + 1, line, pattern_parent_path));
     }
     
     return patterns;
 }
 
+// None for the patterns that can't match anything, or that the platform can't represent
+fn flatten_pattern_line(path: &std::path::Path, line_number: usize, line: &IgnoreFileLine, pattern_parent_path: &std::path::Path) -> Option<OsString> {
+
+    let pattern = parse_ignore_pattern(strip_trailing_whitespaces(&line.text))?;
+
+    // only ASCII characters are stripped around the body, so its offsets are the same in the text and in the raw bytes
+    let body_start = pattern.pattern_body.as_ptr() as usize - line.text.as_ptr() as usize;
+    let body_suffix_len = line.text.len() - body_start - pattern.pattern_body.len();
+    let raw_body = &line.bytes[body_start..line.bytes.len() - body_suffix_len];
+
+    return add_parent_to_ignore_pattern(pattern_parent_path, &pattern, raw_body)
+        .inspect_err(|err| tracing::warn!(path = %path.display(), line = line_number, error = %err, "unrepresentable pattern, skipping it"))
+        .ok();
+}
+
 #[test]
 fn test_read_patterns_from_file() {
 
@@ -39,30 +161,196 @@ fn test_read_patterns_from_file() {
     assert_eq!(read_patterns_from_file(&ignore_file_path, dir_path), ["dir/**/foo", "dir/foo", "dir/**/foo/"]);
 }
 
+#[test]
+fn test_read_patterns_from_encoded_file() {
+
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
 
-pub fn add_parent_to_ignore_pattern(path: &std::path::Path, pattern: IgnorePattern) -> String {
 
-    assert!(pattern.pattern_body.is_empty() == false);
-    assert!(pattern.pattern_body.starts_with('/') == false);
+    let fs_template = Dir::new("dir")
+        .add_dir(Dir::new("bom")
+            .add_file(File::new(".gitignore", b"\xef\xbb\xbffoo\nbar\n".to_vec())))
+        .add_dir(Dir::new("crlf")
+            .add_file(File::new(".gitignore", b"foo\r\n/bar\r\nbaz \r\n".to_vec())));
 
+    let fs = TmpFilesystem::new(&fs_template);
+
+    let read_patterns = |dir: &str| read_patterns_from_file(&fs.path().join("dir").join(dir).join(".gitignore"), std::path::Path::new(dir));
+
+    assert_eq!(read_patterns("bom"), ["bom/**/foo", "bom/**/bar"]);
+    assert_eq!(read_patterns("crlf"), ["crlf/**/foo", "crlf/bar", "crlf/**/baz"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_read_patterns_from_non_utf8_dir() {
+
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
 
-    let mut result = pattern.pattern_body.to_string();
 
-    if pattern.absolute == false {
+    let dir_name = OsString::from_vec(b"dir_\xff\xfe[1]".to_vec());
 
-        result = format!("**/{}", result);
+    let fs_template = Dir::new(dir_name.clone())
+        .add_file(File::new_gitignore(&["foo", "!/bar"]))
+        .add_dir(Dir::new("invalid")
+            .add_file(File::new(".gitignore", b"foo\xff\n!/\xfe\xff/ \r\n# \xff\nbar\r".to_vec())));
+
+    let fs = TmpFilesystem::new(&fs_template);
+
+    let ignore_file_path = fs.path().join(&dir_name).join(".gitignore");
+    let patterns = read_patterns_from_file(&ignore_file_path, std::path::Path::new(&dir_name));
+
+    let patterns = patterns.iter().map(|pattern| pattern.as_bytes()).collect::<Vec<_>>();
+    assert_eq!(patterns, [b"dir_\xff\xfe\\[1\\]/**/foo".as_slice(), b"!dir_\xff\xfe\\[1\\]/bar".as_slice()]);
+
+    // invalid UTF-8 in the patterns is written as is, as git matches it against the raw file names
+    let ignore_file_path = fs.path().join(&dir_name).join("invalid/.gitignore");
+    let patterns = read_patterns_from_file(&ignore_file_path, std::path::Path::new("invalid"));
+
+    let patterns = patterns.iter().map(|pattern| pattern.as_bytes()).collect::<Vec<_>>();
+    assert_eq!(patterns, [b"invalid/**/foo\xff".as_slice(), b"!invalid/\xfe\xff/".as_slice(), b"invalid/**/bar".as_slice()]);
+
+    let patterns = read_annotated_patterns_from_file(&ignore_file_path, std::path::Path::new("invalid"), &fs.path().join(&dir_name));
+
+    let patterns = patterns.iter().map(|pattern| pattern.as_bytes()).collect::<Vec<_>>();
+    assert_eq!(patterns[3], b"# \xff");
+}
+
+
+#[cfg(test)]
+pub fn read_annotated_patterns_from_file(path: &std::path::Path, pattern_parent_path: &std::path::Path, root: &std::path::Path) -> Vec<OsString> {
+
+    assert!(path.is_file());
+
+    return annotated_patterns_from_lines(path, &read_ignore_file(path), pattern_parent_path, root);
+}
+
+// keeps comment lines and blank-line grouping, with a header comment naming the source file relative to the root
+// sources outside of the root, like a configuration file, are named by their full path
+pub fn annotated_patterns_from_lines(path: &std::path::Path, file_lines: &[IgnoreFileLine], pattern_parent_path: &std::path::Path, root: &std::path::Path) -> Vec<OsString> {
+
+    let mut header = OsString::from("# from ");
+    header.push(path.strip_prefix(root).unwrap_or(path).as_os_str());
+
+    let mut lines = vec![header];
+
+    for (idx, line) in file_lines.iter().enumerate() {
+
+        warn_lossy_line(path, idx + 1, line);
+
+        if filter_ignore_line(&line.text) {
+
+            lines.extend(flatten_pattern_line(path, idx + 1, line, pattern_parent_path));
+        }
+        else if line.text.starts_with('#') {
+
+            lines.push(bytes_to_os_string(line.bytes.clone()).unwrap_or_else(|_| OsString::from(&line.text)));
+        }
+        // collapse blank line runs, ignoring the ones right after the header
+        else if lines.len() > 1 && lines.last().is_some_and(|last| last.is_empty()) == false {
+
+            lines.push(OsString::new());
+        }
+    }
+
+    if lines.last().is_some_and(|last| last.is_empty()) {
+
+        lines.pop();
     }
 
+    return lines;
+}
+
+#[test]
+fn test_read_annotated_patterns_from_file() {
+
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["", "# build output", "foo", "  ", "", "# logs", "bar", "!baz", "", ""]))
+        .add_dir(Dir::new("sub")
+            .add_file(File::new_gitignore(&["foo"])));
+
+    let fs = TmpFilesystem::new(&fs_template);
+    let root = fs.path().join("dir");
+
+    let expected = ["# from .gitignore", "# build output", "dir/**/foo", "", "# logs", "dir/**/bar", "!dir/**/baz"];
+    assert_eq!(read_annotated_patterns_from_file(&root.join(".gitignore"), std::path::Path::new("dir"), &root), expected);
+
+    // the header names the file relative to the root
+    assert_eq!(read_annotated_patterns_from_file(&root.join("sub/.gitignore"), std::path::Path::new("dir/sub"), &root), ["# from sub/.gitignore", "dir/sub/**/foo"]);
+    assert_eq!(read_annotated_patterns_from_file(&root.join("sub/.gitignore"), std::path::Path::new("dir/sub"), &root.join("sub"))[0], "# from .gitignore");
+
+    // sources outside of the root keep their full path
+    let outside_root = fs.path().join("other");
+    let header = read_annotated_patterns_from_file(&root.join(".gitignore"), std::path::Path::new("dir"), &outside_root).swap_remove(0);
+
+    let mut expected_header = OsString::from("# from ");
+    expected_header.push(root.join(".gitignore"));
+    assert_eq!(header, expected_header);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_annotated_patterns_non_utf8_header() {
+
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+    let root = std::path::Path::new("/root");
+    let dir = root.join(OsString::from_vec(b"dir_\xff".to_vec()));
+    let lines = decode_ignore_file(b"foo\n");
+
+    let patterns = annotated_patterns_from_lines(&dir.join(".gitignore"), &lines, &dir, root);
+    assert_eq!(patterns[0].as_bytes(), b"# from dir_\xff/.gitignore");
+}
+
+
+fn warn_lossy_line(path: &std::path::Path, line_number: usize, line: &IgnoreFileLine) {
+
+    if line.lossy {
+
+        tracing::warn!(path = %path.display(), line = line_number, "invalid UTF-8 in ignore file, the pattern is written as is but doesn't prune the traversal");
+    }
+}
+
+
+// the parent path is carried as raw bytes, so non-UTF-8 directory names are kept losslessly
+// the body is given as the raw bytes of the ignore file line, which can differ from the parsed one when it isn't UTF-8
+pub fn add_parent_to_ignore_pattern(path: &std::path::Path, pattern: &IgnorePattern, pattern_body: &[u8]) -> std::io::Result<OsString> {
+
+    assert!(pattern_body.is_empty() == false);
+    assert!(pattern_body.starts_with(b"/") == false);
+
+
     // add parent path
-    result = format!("{}/{}", path.to_str().expect("non-UTF-8 path"), result);
-    result = result.replace("//", "/");
+    let mut result = escape_path_prefix(path.as_os_str().as_encoded_bytes());
+    result.push(b'/');
+
+    if pattern.absolute == false && pattern.has_double_star_prefix() == false {
+
+        result.extend_from_slice(b"**/");
+    }
+
+    result.extend_from_slice(pattern_body);
+
+    if pattern.dir_only {
+
+        result.push(b'/');
+    }
+
+    result = replace_double_slashes(&result);
 
     if pattern.negated == true {
 
-        result = format!("!{}", result);
+        result.insert(0, b'!');
     }
 
-    return result;
+    return bytes_to_os_string(result);
 }
 
 #[test]
@@ -71,7 +359,8 @@ fn test_add_parent_to_ignore_pattern() {
     #[track_caller]
     fn assert_pattern(base_path: &str, input: &str, expected_output: &str) {
 
-        assert_eq!(&add_parent_to_ignore_pattern(std::path::Path::new(base_path), parse_ignore_pattern(input)), expected_output);
+        let pattern = parse_ignore_pattern(input).unwrap();
+        assert_eq!(add_parent_to_ignore_pattern(std::path::Path::new(base_path), &pattern, pattern.pattern_body.as_bytes()).unwrap(), expected_output);
     }
 
     let path = "foo/bar";
@@ -85,18 +374,188 @@ fn test_add_parent_to_ignore_pattern() {
     
     assert_pattern("/foo/bar", "/foobar", "/foo/bar/foobar");
     assert_pattern("./foo/bar", "/foobar", "./foo/bar/foobar");
+
+    // escapes and double asterisks
+    assert_pattern(path, "\\#foobar", "foo/bar/**/#foobar");
+    assert_pattern(path, "\\!foobar", "foo/bar/**/!foobar");
+    assert_pattern(path, "**/foobar", "foo/bar/**/foobar");
+    assert_pattern(path, "**/foobar/", "foo/bar/**/foobar/");
+    assert_pattern(path, "**", "foo/bar/**");
+    assert_pattern(path, "foobar/**", "foo/bar/foobar/**");
+    assert_pattern(path, "foo**", "foo/bar/**/foo**");
+
+    // special characters in the parent path
+    assert_pattern("build[1]", "foo*", "build\\[1\\]/**/foo*");
+    assert_pattern("a*b/c?", "/foo", "a\\*b/c\\?/foo");
+    assert_pattern("a\\b", "/foo", "a\\\\b/foo");
+    assert_pattern("#tmp", "foo", "\\#tmp/**/foo");
+    assert_pattern("!x", "foo", "\\!x/**/foo");
+    assert_pattern("!x", "!foo", "!\\!x/**/foo");
+    assert_pattern("dir/#tmp", "/foo", "dir/#tmp/foo");
+}
+
+#[test]
+fn test_special_characters_in_dir_names() {
+
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
+    use ignore::gitignore::GitignoreBuilder;
+
+
+    for dir_name in ["build[1]", "a*b", "c?d", "#tmp", "!x", "back\\slash"] {
+
+        let fs_template = Dir::new(dir_name)
+            .add_file(File::new_gitignore(&["foo", "/bar"]))
+            .add_file(File::new_empty("foo"))
+            .add_file(File::new_empty("bar"));
+
+        let fs = TmpFilesystem::new(&fs_template);
+
+        let ignore_file_path = fs.path().join(dir_name).join(".gitignore");
+        let patterns = read_patterns_from_file(&ignore_file_path, std::path::Path::new(dir_name));
+
+        // the flattened patterns must only match inside the original directory
+        let mut builder = GitignoreBuilder::new(fs.path());
+        patterns.iter().for_each(|pattern| { builder.add_line(None, pattern.to_str().unwrap()).expect("invalid flattened pattern"); });
+        let matcher = builder.build().expect("matcher build unexpected error");
+
+        let dir_path = fs.path().join(dir_name);
+        let decoy_path = fs.path().join("decoy");
+
+        assert!(matcher.matched(dir_path.join("foo"), false).is_ignore(), "dir name: {}", dir_name);
+        assert!(matcher.matched(dir_path.join("bar"), false).is_ignore(), "dir name: {}", dir_name);
+        assert!(matcher.matched(decoy_path.join("foo"), false).is_none(), "dir name: {}", dir_name);
+        assert!(matcher.matched(decoy_path.join("bar"), false).is_none(), "dir name: {}", dir_name);
+    }
+}
+
+
+// escapes glob metacharacters, and a leading comment or negation marker, of a literal path
+pub fn escape_path_prefix(path: &[u8]) -> Vec<u8> {
+
+    let mut result = Vec::with_capacity(path.len());
+
+    if path.starts_with(b"#") || path.starts_with(b"!") {
+
+        result.push(b'\\');
+    }
+
+    for byte in path {
+
+        if matches!(byte, b'*' | b'?' | b'[' | b']' | b'\\') {
+
+            result.push(b'\\');
+        }
+
+        result.push(*byte);
+    }
+
+    return result;
+}
+
+#[test]
+fn test_escape_path_prefix() {
+
+    #[track_caller]
+    fn assert_escape(path: &str, expected: &str) {
+
+        assert_eq!(escape_path_prefix(path.as_bytes()), expected.as_bytes());
+    }
+
+    assert_escape("", "");
+    assert_escape("foo/bar", "foo/bar");
+    assert_escape("build[1]", "build\\[1\\]");
+    assert_escape("a*b?", "a\\*b\\?");
+    assert_escape("a\\b", "a\\\\b");
+    assert_escape("#tmp/#foo", "\\#tmp/#foo");
+    assert_escape("!x/!y", "\\!x/!y");
+    assert_escape("./foo", "./foo");
+}
+
+
+// same as "str::replace(\"//\", \"/\")", but over raw bytes
+fn replace_double_slashes(bytes: &[u8]) -> Vec<u8> {
+
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+
+        result.push(bytes[idx]);
+
+        if bytes[idx..].starts_with(b"//") {
+
+            idx += 2;
+        }
+        else {
+
+            idx += 1;
+        }
+    }
+
+    return result;
+}
+
+#[test]
+fn test_replace_double_slashes() {
+
+    #[track_caller]
+    fn assert_replace(input: &str, expected: &str) {
+
+        assert_eq!(replace_double_slashes(input.as_bytes()), input.replace("//", "/").as_bytes());
+        assert_eq!(replace_double_slashes(input.as_bytes()), expected.as_bytes());
+    }
+
+    assert_replace("", "");
+    assert_replace("/", "/");
+    assert_replace("//", "/");
+    assert_replace("///", "//");
+    assert_replace("////", "//");
+    assert_replace("a//b/c", "a/b/c");
+}
+
+
+#[cfg(unix)]
+pub fn bytes_to_os_string(bytes: Vec<u8>) -> std::io::Result<OsString> {
+
+    use std::os::unix::ffi::OsStringExt;
+    return Ok(OsString::from_vec(bytes));
+}
+
+// other platforms can't hold arbitrary bytes in an "OsString", only UTF-8 is accepted
+#[cfg(not(unix))]
+pub fn bytes_to_os_string(bytes: Vec<u8>) -> std::io::Result<OsString> {
+
+    return String::from_utf8(bytes)
+        .map(OsString::from)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("non-UTF-8 path: {}", String::from_utf8_lossy(err.as_bytes()))));
 }
 
 
 #[derive(PartialEq, Eq, Debug)]
 pub struct IgnorePattern<'a> {
 
-    negated: bool,
-    absolute: bool,
-    pattern_body: &'a str
+    pub negated: bool,
+
+    // relative to the ignore file directory, instead of matching at any depth
+    pub absolute: bool,
+    pub dir_only: bool,
+
+    // without the negation, leading slash, trailing slash and line start escapes
+    pub pattern_body: &'a str
 }
 
-pub fn parse_ignore_pattern(mut pattern: &str) -> IgnorePattern {
+impl IgnorePattern<'_> {
+
+    pub fn has_double_star_prefix(&self) -> bool {
+
+        return tokenize_pattern(self.pattern_body).first() == Some(&PatternToken::DoubleStar);
+    }
+}
+
+// reference: https://git-scm.com/docs/gitignore#_pattern_format
+// returns None for patterns that can't match anything, like "/" or "!"
+pub fn parse_ignore_pattern(mut pattern: &str) -> Option<IgnorePattern<'_>> {
 
     assert!(pattern.is_empty() == false);
     assert!(pattern.starts_with('#') == false);
@@ -104,42 +563,195 @@ pub fn parse_ignore_pattern(mut pattern: &str) -> IgnorePattern {
 
     let mut negated = false;
     let mut absolute = false;
+    let mut dir_only = false;
+
+    // the escapes are only needed at the line start, where the prefix will be added
+    if pattern.starts_with("\\!") || pattern.starts_with("\\#") {
 
-    if pattern.starts_with('!') {
+        pattern = &pattern[1..];
+    }
+    else if pattern.starts_with('!') {
 
         negated = true;
         pattern = &pattern[1..];
     }
-    
-    if let Some((idx, _)) = pattern.char_indices().find(|(idx, ch)| (*ch == '/') && (*idx != (pattern.len() - 1))) {
+
+    if pattern.starts_with('/') {
 
         absolute = true;
+        pattern = &pattern[1..];
+    }
 
-        // remove the slash if it appears at the beginning
-        if idx == 0 {
+    if pattern.ends_with('/') {
 
-            pattern = &pattern[1..];
-        }
+        dir_only = true;
+        pattern = &pattern[..pattern.len() - 1];
+    }
+
+    if pattern.is_empty() {
+
+        return None;
+    }
+
+    // a leading "**/" already matches at any depth, so it doesn't anchor the pattern
+    let tokens = tokenize_pattern(pattern);
+    let anchoring_tokens = match tokens.as_slice() {
+
+        [PatternToken::DoubleStar, PatternToken::Slash, rest @ ..] => rest,
+        tokens => tokens
+    };
+
+    if anchoring_tokens.contains(&PatternToken::Slash) {
+
+        absolute = true;
     }
     
-    return IgnorePattern{negated, absolute, pattern_body: pattern};
+    return Some(IgnorePattern{negated, absolute, dir_only, pattern_body: pattern});
 }
 
 #[test]
 fn test_parse_ignore_pattern() {
 
     #[track_caller]
-    fn assert_parse(pattern: &str, negated: bool, absolute: bool, body: &str) {
+    fn assert_parse(pattern: &str, negated: bool, absolute: bool, dir_only: bool, body: &str) {
 
-        assert_eq!(parse_ignore_pattern(pattern), IgnorePattern{negated, absolute, pattern_body: body});
+        assert_eq!(parse_ignore_pattern(pattern), Some(IgnorePattern{negated, absolute, dir_only, pattern_body: body}));
     }
 
-    assert_parse("foo", false, false, "foo");
-    assert_parse("!foo", true, false, "foo");
-    assert_parse("!/foo", true, true, "foo");
-    assert_parse("/foo", false, true, "foo");
-    assert_parse("foo/bar", false, true, "foo/bar");
-    assert_parse("foo/", false, false, "foo/");
+    assert_parse("foo", false, false, false, "foo");
+    assert_parse("!foo", true, false, false, "foo");
+    assert_parse("!/foo", true, true, false, "foo");
+    assert_parse("/foo", false, true, false, "foo");
+    assert_parse("foo/bar", false, true, false, "foo/bar");
+    assert_parse("foo/", false, false, true, "foo");
+    assert_parse("/foo/", false, true, true, "foo");
+    assert_parse("!foo/bar/", true, true, true, "foo/bar");
+
+    // escapes
+    assert_parse("\\#foo", false, false, false, "#foo");
+    assert_parse("\\!foo", false, false, false, "!foo");
+    assert_parse("\\!/foo", false, true, false, "!/foo");
+    assert_parse("!\\!foo", true, false, false, "\\!foo");
+    assert_parse("!#foo", true, false, false, "#foo");
+    assert_parse("foo\\ ", false, false, false, "foo\\ ");
+    assert_parse("\\\\foo", false, false, false, "\\\\foo");
+
+    // double asterisks
+    assert_parse("**/foo", false, false, false, "**/foo");
+    assert_parse("**/foo/", false, false, true, "**/foo");
+    assert_parse("**/foo/bar", false, true, false, "**/foo/bar");
+    assert_parse("/**/foo", false, true, false, "**/foo");
+    assert_parse("foo/**", false, true, false, "foo/**");
+    assert_parse("foo/**/bar", false, true, false, "foo/**/bar");
+    assert_parse("**", false, false, false, "**");
+    assert_parse("foo**", false, false, false, "foo**");
+
+    // bracket expressions
+    assert_parse("[/]foo", false, false, false, "[/]foo");
+    assert_parse("[ab]/foo", false, true, false, "[ab]/foo");
+
+    // patterns that can't match
+    assert_eq!(parse_ignore_pattern("/"), None);
+    assert_eq!(parse_ignore_pattern("!"), None);
+    assert_eq!(parse_ignore_pattern("!/"), None);
+    assert_eq!(parse_ignore_pattern("//"), None);
+}
+
+#[test]
+fn test_gitignore_documented_examples() {
+
+    use ignore::gitignore::GitignoreBuilder;
+
+    // paths ending with a slash are directories, all of them are relative to the ignore file directory
+    #[track_caller]
+    fn assert_examples(patterns: &[&str], matched: &[&str], unmatched: &[&str]) {
+
+        let mut original_builder = GitignoreBuilder::new("dir");
+        let mut flattened_builder = GitignoreBuilder::new(".");
+
+        for pattern in patterns {
+
+            original_builder.add_line(None, pattern).expect("invalid pattern");
+
+            if let Some(parsed_pattern) = parse_ignore_pattern(pattern) {
+
+                let flattened_pattern = add_parent_to_ignore_pattern(std::path::Path::new("sub/dir"), &parsed_pattern, parsed_pattern.pattern_body.as_bytes()).unwrap();
+                flattened_builder.add_line(None, flattened_pattern.to_str().unwrap()).expect("invalid flattened pattern");
+            }
+        }
+
+        let original_matcher = original_builder.build().unwrap();
+        let flattened_matcher = flattened_builder.build().unwrap();
+
+        let is_ignored = |path: &str| {
+
+            let is_dir = path.ends_with('/');
+            let path = path.trim_end_matches('/');
+
+            let original = original_matcher.matched_path_or_any_parents(std::path::Path::new("dir").join(path), is_dir).is_ignore();
+            let flattened = flattened_matcher.matched_path_or_any_parents(std::path::Path::new("sub/dir").join(path), is_dir).is_ignore();
+
+            assert_eq!(original, flattened, "patterns: {:?}, path: {}", patterns, path);
+            return flattened;
+        };
+
+        for path in matched {
+
+            assert!(is_ignored(path), "patterns: {:?}, path: {}", patterns, path);
+        }
+
+        for path in unmatched {
+
+            assert!(is_ignored(path) == false, "patterns: {:?}, path: {}", patterns, path);
+        }
+    }
+
+    // "hello.*" matches any file or directory whose name begins with "hello."
+    assert_examples(&["hello.*"], &["hello.c", "a/hello.java", "hello.d/"], &["hello", "ahello.c"]);
+
+    // "foo/" matches a directory and paths underneath it, but not a regular file
+    assert_examples(&["foo/"], &["foo/", "a/foo/", "foo/bar"], &["foo", "a/foo"]);
+
+    // "doc/frotz/" and "/doc/frotz/" only match at the ignore file level
+    assert_examples(&["doc/frotz/"], &["doc/frotz/", "doc/frotz/a"], &["a/doc/frotz/", "doc/frotz"]);
+    assert_examples(&["/doc/frotz/"], &["doc/frotz/"], &["a/doc/frotz/"]);
+
+    // "frotz/" matches at any level
+    assert_examples(&["frotz/"], &["frotz/", "a/frotz/"], &["frotz"]);
+
+    // "/bar" only matches at the ignore file level
+    assert_examples(&["/bar"], &["bar", "bar/"], &["a/bar"]);
+
+    // "foo/*" matches "foo/test.json" and "foo/bar", but not "foo/bar/hello.c"
+    assert_examples(&["foo/*"], &["foo/test.json", "foo/bar/"], &["a/foo/test.json", "foo"]);
+
+    // leading "**/"
+    assert_examples(&["**/foo"], &["foo", "a/foo", "a/b/foo/"], &["foobar", "a/foobar"]);
+    assert_examples(&["**/foo/bar"], &["foo/bar", "a/foo/bar", "a/b/foo/bar/"], &["foo/a/bar"]);
+
+    // trailing "/**"
+    assert_examples(&["abc/**"], &["abc/a", "abc/a/b/"], &["abc", "a/abc/a"]);
+
+    // middle "/**/"
+    assert_examples(&["a/**/b"], &["a/b", "a/x/b", "a/x/y/b"], &["x/a/b", "a/bb"]);
+
+    // other consecutive asterisks are regular asterisks
+    assert_examples(&["foo**"], &["foo", "foobar", "a/foobar"], &["bar"]);
+
+    // escapes
+    assert_examples(&["\\#foo"], &["#foo", "a/#foo"], &["foo"]);
+    assert_examples(&["\\!foo"], &["!foo", "a/!foo"], &["foo"]);
+    assert_examples(&["foo\\ "], &["foo "], &["foo"]);
+    assert_examples(&["\\*"], &["*"], &["foo"]);
+
+    // single character and bracket expressions
+    assert_examples(&["fo?"], &["foo", "a/fob"], &["fo", "fooo"]);
+    assert_examples(&["[ab]c"], &["ac", "x/bc"], &["cc"]);
+    assert_examples(&["[!ab]c"], &["cc"], &["ac", "bc"]);
+
+    // negations
+    assert_examples(&["*.log", "!important.log"], &["debug.log", "a/debug.log"], &["important.log", "a/important.log"]);
+    assert_examples(&["/*", "!/foo", "/foo/*", "!/foo/bar"], &["baz", "foo/baz"], &["foo/bar", "foo/bar/a"]);
 }
 
 
@@ -198,11 +810,10 @@ pub fn strip_trailing_whitespaces(line: &str) -> &str {
         return "";
     }
 
-
+    // byte positions, the line can hold multi-byte characters
     let strip_position = stripped.len();
-    let get_line_char = |idx| line.chars().nth(idx).expect("unexpected string size");
 
-    if stripped.ends_with('\\') && get_line_char(stripped.len()) == ' ' {
+    if stripped.ends_with('\\') && line.as_bytes()[strip_position] == b' ' {
 
         return &line[..strip_position + 1];
     }
@@ -231,4 +842,9 @@ fn test_strip_trailing_whitespaces() {
     assert_strip("foo bar", "foo bar");
     assert_strip(" foo bar", " foo bar");
     assert_strip("foo bar ", "foo bar");
+    assert_strip("é\\ ", "é\\ ");
+    assert_strip("é\\  ", "é\\ ");
+    assert_strip("é ", "é");
+    assert_strip("\u{fffd}\\ ", "\u{fffd}\\ ");
+    assert_strip("\u{fffd}\\ \u{fffd} ", "\u{fffd}\\ \u{fffd}");
 }
@@ -0,0 +1,244 @@
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use crate::file_system::MemoryFileSystem;
+
+
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ArchiveFormat {
+
+    Tar,
+    TarGz,
+    Zip
+}
+
+impl ArchiveFormat {
+
+    // detected from the file name
+    pub fn from_path(path: &Path) -> Option<Self> {
+
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+
+        return match name {
+
+            name if name.ends_with(".tar") => Some(Self::Tar),
+            name if name.ends_with(".tar.gz") || name.ends_with(".tgz") => Some(Self::TarGz),
+            name if name.ends_with(".zip") => Some(Self::Zip),
+            _ => None
+        };
+    }
+}
+
+
+// directories and ignore files of the archive, placed under the root as if it were unpacked there
+pub fn load_archive(path: &Path, format: ArchiveFormat, root: &Path, ignore_file_name: &str) -> anyhow::Result<MemoryFileSystem> {
+
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+
+    return match format {
+
+        ArchiveFormat::Tar => load_tar(file, root, ignore_file_name),
+        ArchiveFormat::TarGz => load_tar(flate2::read::GzDecoder::new(file), root, ignore_file_name),
+        ArchiveFormat::Zip => load_zip(file, root, ignore_file_name)
+    };
+}
+
+// only the content of the ignore files is kept, the other files are empty
+fn load_tar(reader: impl Read, root: &Path, ignore_file_name: &str) -> anyhow::Result<MemoryFileSystem> {
+
+    let mut fs = MemoryFileSystem::new();
+    fs.add_dir(root);
+
+    let mut archive = tar::Archive::new(reader);
+
+    for entry in archive.entries()? {
+
+        let mut entry = entry?;
+        let Some(path) = entry_path(root, &entry.path()?) else { continue; };
+
+        match entry.header().entry_type() {
+
+            tar::EntryType::Directory => fs.add_dir(&path),
+            tar::EntryType::Symlink => fs.add_symlink(&path),
+
+            // a hard link shares the content of a previous entry
+            tar::EntryType::Link => {
+
+                let target = entry.link_name()?.and_then(|target| entry_path(root, &target));
+                let content = target.and_then(|target| crate::file_system::FileSystem::read_file(&fs, &target).ok()).unwrap_or_default();
+
+                fs.add_file(&path, content);
+            },
+            entry_type if entry_type.is_file() => {
+
+                let mut content = Vec::new();
+
+                if path.file_name().is_some_and(|name| name == ignore_file_name) {
+
+                    entry.read_to_end(&mut content)?;
+                }
+
+                fs.add_file(&path, content);
+            },
+
+            // devices, fifos... aren't directories nor ignore files
+            _ => {}
+        }
+    }
+
+    return Ok(fs);
+}
+
+fn load_zip(reader: impl Read + std::io::Seek, root: &Path, ignore_file_name: &str) -> anyhow::Result<MemoryFileSystem> {
+
+    let mut fs = MemoryFileSystem::new();
+    fs.add_dir(root);
+
+    let mut archive = zip::ZipArchive::new(reader)?;
+
+    for idx in 0..archive.len() {
+
+        let mut entry = archive.by_index(idx)?;
+        let Some(path) = entry.enclosed_name().and_then(|path| entry_path(root, &path)) else { continue; };
+
+        if entry.is_dir() {
+
+            fs.add_dir(&path);
+        }
+        else if entry.is_symlink() {
+
+            fs.add_symlink(&path);
+        }
+        else {
+
+            let mut content = Vec::new();
+
+            if path.file_name().is_some_and(|name| name == ignore_file_name) {
+
+                entry.read_to_end(&mut content)?;
+            }
+
+            fs.add_file(&path, content);
+        }
+    }
+
+    return Ok(fs);
+}
+
+// entries escaping the root are skipped, as "tar" and "unzip" do by default
+fn entry_path(root: &Path, path: &Path) -> Option<PathBuf> {
+
+    let mut entry_path = root.to_owned();
+
+    for component in path.components() {
+
+        match component {
+
+            Component::Normal(name) => entry_path.push(name),
+            Component::CurDir => {},
+            _ => return None
+        }
+    }
+
+    // the root itself, as in a "./" entry
+    if entry_path == root {
+
+        return None;
+    }
+
+    return Some(entry_path);
+}
+
+#[test]
+fn test_entry_path() {
+
+    let root = Path::new(".");
+
+    assert_eq!(entry_path(root, Path::new("a/b")), Some(PathBuf::from("./a/b")));
+    assert_eq!(entry_path(root, Path::new("./a/b/")), Some(PathBuf::from("./a/b")));
+    assert_eq!(entry_path(root, Path::new("./")), None);
+    assert_eq!(entry_path(root, Path::new("/etc/passwd")), None);
+    assert_eq!(entry_path(root, Path::new("a/../../b")), None);
+}
+
+#[test]
+fn test_archive_flatten() {
+
+    use crate::filesystem::archive_builder::ArchiveBuilder;
+    use crate::filesystem::template::{Dir, File};
+    use crate::job::{self, FlattenOptions};
+
+
+    let fs_template = Dir::new("project")
+        .add_file(File::new_gitignore(&["build/", "*.log", "!keep.log"]))
+        .add_file(File::new("README", b"build/\n".to_vec()))
+        .add_dir(Dir::new("a")
+            .add_file(File::new_gitignore(&["foo", "/bar"]))
+            .add_dir(Dir::new("b")
+                .add_file(File::new_gitignore(&["baz"]))))
+        .add_dir(Dir::new("build")
+            .add_file(File::new_gitignore(&["hidden"])))
+        .add_dir(Dir::new("empty"));
+
+    let root = Path::new(".");
+    let unpacked_fs = MemoryFileSystem::from_template(root, &fs_template);
+    let expected = job::flatten(&unpacked_fs, root, ".gitignore", FlattenOptions{keep_comments: true, ..Default::default()});
+
+    assert!(expected.is_empty() == false);
+
+    let archives = [
+        (ArchiveFormat::Tar, ArchiveBuilder::build_tar(&fs_template)),
+        (ArchiveFormat::TarGz, ArchiveBuilder::build_tar_gz(&fs_template)),
+        (ArchiveFormat::Zip, ArchiveBuilder::build_zip(&fs_template))
+    ];
+
+    for (format, content) in archives {
+
+        let archive_fs = match format {
+
+            ArchiveFormat::Tar => load_tar(content.as_slice(), root, ".gitignore"),
+            ArchiveFormat::TarGz => load_tar(flate2::read::GzDecoder::new(content.as_slice()), root, ".gitignore"),
+            ArchiveFormat::Zip => load_zip(std::io::Cursor::new(content), root, ".gitignore")
+        }.unwrap();
+
+        assert_eq!(job::flatten(&archive_fs, root, ".gitignore", FlattenOptions{keep_comments: true, ..Default::default()}), expected, "{:?}", format);
+    }
+}
+
+#[test]
+fn test_load_archive() {
+
+    use crate::file_system::{EntryType, FileSystem};
+    use crate::filesystem::archive_builder::ArchiveBuilder;
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("project")
+        .add_file(File::new_gitignore(&["foo"]))
+        .add_file(File::new("data", b"content".to_vec()));
+
+    let tmp_fs = TmpFilesystem::new(&Dir::new("archives"));
+    let archive_path = tmp_fs.path().join("archives/project.TGZ");
+    std::fs::write(&archive_path, ArchiveBuilder::build_tar_gz(&fs_template)).unwrap();
+
+    let format = ArchiveFormat::from_path(&archive_path).expect("unknown archive format");
+    let fs = load_archive(&archive_path, format, Path::new("/mnt"), ".gitignore").unwrap();
+
+    assert_eq!(fs.read_dir(Path::new("/mnt/project")).unwrap(), [
+        (PathBuf::from("/mnt/project/.gitignore"), EntryType::File),
+        (PathBuf::from("/mnt/project/data"), EntryType::File)
+    ]);
+
+    // only the ignore files are read
+    assert_eq!(fs.read_file(Path::new("/mnt/project/.gitignore")).unwrap(), b"foo");
+    assert_eq!(fs.read_file(Path::new("/mnt/project/data")).unwrap(), b"");
+
+    assert_eq!(ArchiveFormat::from_path(Path::new("a.tar")), Some(ArchiveFormat::Tar));
+    assert_eq!(ArchiveFormat::from_path(Path::new("a.tar.gz")), Some(ArchiveFormat::TarGz));
+    assert_eq!(ArchiveFormat::from_path(Path::new("a.zip")), Some(ArchiveFormat::Zip));
+    assert_eq!(ArchiveFormat::from_path(Path::new("a.gz")), None);
+    assert_eq!(ArchiveFormat::from_path(Path::new("dir")), None);
+
+    assert!(load_archive(&tmp_fs.path().join("archives/missing.tar"), ArchiveFormat::Tar, Path::new("/mnt"), ".gitignore").is_err());
+}
@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use crate::absolute_ignore;
+use crate::file_system::{EntryType, FileSystem};
+use crate::ignore_node::IgnoreNode;
+use crate::job::{self, Job, VisitedDir};
+
+
+
+const CACHE_VERSION: u32 = 4;
+
+// file timestamps come from a coarse clock, entries modified this close to the scan are checked again on the next run
+const RACY_MARGIN: Duration = Duration::from_secs(1);
+
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct CachedIgnoreFile {
+
+    mtime: Option<SystemTime>,
+    size: u64,
+    hash: u64,
+
+    // the ones given to the matcher
+    lines: Vec<String>,
+    patterns: Vec<OsString>
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CacheEntry {
+
+    mtime: Option<SystemTime>,
+
+    // every subdirectory, the excluded ones are filtered on each run since ancestor ignore files may change
+    child_dir_names: Vec<OsString>,
+    ignore_file: Option<CachedIgnoreFile>
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CacheFile {
+
+    version: u32,
+
+    // the entries are keyed by the paths as traversed, which may be relative to the working directory
+    root: OsString,
+    ignore_file_name: String,
+    keep_comments: bool,
+    scan_started: SystemTime,
+    entries: Vec<(OsString, CacheEntry)>
+}
+
+
+// directory listings and ignore files of a previous run, keyed by directory path
+pub struct FlattenCache {
+
+    root: PathBuf,
+    ignore_file_name: String,
+    keep_comments: bool,
+    scan_started: SystemTime,
+    previous_scan_started: SystemTime,
+    previous_entries: HashMap<PathBuf, CacheEntry>,
+    entries: HashMap<PathBuf, CacheEntry>,
+
+    // directories and ignore files read during this run
+    pub read_dirs: Vec<PathBuf>,
+    pub read_ignore_files: Vec<PathBuf>
+}
+
+impl FlattenCache {
+
+    // a missing or incompatible cache file gives an empty cache, as does one written for another root
+    pub fn load(path: &Path, root: &Path, ignore_file_name: &str, keep_comments: bool) -> Self {
+
+        let mut cache = Self{
+            root: std::path::absolute(root).unwrap_or_else(|_| root.to_owned()),
+            ignore_file_name: ignore_file_name.to_owned(),
+            keep_comments,
+            scan_started: SystemTime::now(),
+            previous_scan_started: SystemTime::UNIX_EPOCH,
+            previous_entries: HashMap::new(),
+            entries: HashMap::new(),
+            read_dirs: Vec::new(),
+            read_ignore_files: Vec::new()
+        };
+
+        let content = match std::fs::read(path) {
+
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return cache,
+            Err(err) => {
+
+                tracing::warn!(path = %path.display(), error = %err, "cache reading failed, ignoring it");
+                return cache;
+            }
+        };
+
+        let cache_file = match serde_json::from_slice::<CacheFile>(&content) {
+
+            Ok(cache_file) => cache_file,
+            Err(err) => {
+
+                tracing::warn!(path = %path.display(), error = %err, "invalid cache, ignoring it");
+                return cache;
+            }
+        };
+
+        let compatible = cache_file.version == CACHE_VERSION && cache_file.root == cache.root.as_os_str()
+            && cache_file.ignore_file_name == ignore_file_name && cache_file.keep_comments == keep_comments;
+
+        if compatible {
+
+            cache.previous_scan_started = cache_file.scan_started;
+            cache.previous_entries = cache_file.entries.into_iter().map(|(path, entry)| (PathBuf::from(path), entry)).collect();
+        }
+
+        return cache;
+    }
+
+    // only the directories visited during this run are kept
+    pub fn save(self, path: &Path) -> anyhow::Result<()> {
+
+        let mut entries = self.entries.into_iter().map(|(path, entry)| (path.into_os_string(), entry)).collect::<Vec<_>>();
+        entries.sort_by(|(path1, _), (path2, _)| path1.cmp(path2));
+
+        let cache_file = CacheFile{
+            version: CACHE_VERSION,
+            root: self.root.into_os_string(),
+            ignore_file_name: self.ignore_file_name,
+            keep_comments: self.keep_comments,
+            scan_started: self.scan_started,
+            entries
+        };
+
+        crate::output::write_file_atomically(path, &serde_json::to_vec(&cache_file)?)?;
+
+        return Ok(());
+    }
+
+    fn is_fresh(&self, previous_mtime: Option<SystemTime>, mtime: Option<SystemTime>) -> bool {
+
+        return match (previous_mtime, mtime) {
+
+            (Some(previous_mtime), Some(mtime)) => previous_mtime == mtime && mtime + RACY_MARGIN < self.previous_scan_started,
+            _ => false
+        };
+    }
+
+    // same as "job::process_job", reusing the unchanged listings and ignore files of the previous run
+    pub fn process_job(&mut self, fs: &dyn FileSystem, root: &Path, job: Job) -> VisitedDir {
+
+        let Job{path, ignore_context: parent_context} = job;
+        tracing::debug!(path = %path.display(), "directory visited");
+
+        let previous_entry = self.previous_entries.remove(&path);
+        let mtime = fs.stamp(&path).map(|stamp| stamp.mtime);
+
+        let child_dir_names = match previous_entry {
+
+            Some(ref entry) if self.is_fresh(entry.mtime, mtime) => entry.child_dir_names.clone(),
+            _ => {
+
+                self.read_dirs.push(path.clone());
+
+                fs.read_dir(&path).unwrap_or_else(|err| cache_io_error_handler(&path, err)).into_iter()
+                    .filter(|(_, entry_type)| *entry_type == EntryType::Dir)
+                    .map(|(dir, _)| dir.file_name().expect("invalid directory entry").to_owned())
+                    .collect()
+            }
+        };
+
+        let ignore_file_path = path.join(&self.ignore_file_name);
+        let ignore_file = self.load_ignore_file(fs, root, &ignore_file_path, &path, previous_entry.and_then(|entry| entry.ignore_file));
+
+        let ignore_context = match ignore_file {
+
+            Some(ref ignore_file) => Some(IgnoreNode::from_lines(&ignore_file_path, &ignore_file.lines, parent_context.clone())),
+            None => parent_context.clone()
+        };
+
+        let (child_dirs, pruned_dirs) = job::split_pruned_dirs(&ignore_context, child_dir_names.iter().map(|name| path.join(name)));
+
+        let patterns = ignore_file.as_ref().map(|ignore_file| ignore_file.patterns.clone());
+        self.entries.insert(path.clone(), CacheEntry{mtime, child_dir_names, ignore_file});
+
+        return VisitedDir{path, parent_context, ignore_context, patterns, child_dirs, pruned_dirs};
+    }
+
+    fn load_ignore_file(&mut self, fs: &dyn FileSystem, root: &Path, path: &Path, dir: &Path, previous: Option<CachedIgnoreFile>) -> Option<CachedIgnoreFile> {
+
+        if fs.entry_type(path) != Some(EntryType::File) {
+
+            return None;
+        }
+
+        let stamp = fs.stamp(path);
+        let mtime = stamp.map(|stamp| stamp.mtime);
+
+        if let Some(ref previous) = previous {
+
+            if self.is_fresh(previous.mtime, mtime) && stamp.is_some_and(|stamp| stamp.size == previous.size) {
+
+                return Some(previous.clone());
+            }
+        }
+
+        self.read_ignore_files.push(path.to_owned());
+        tracing::info!(path = %path.display(), "ignore file loaded");
+
+        let content = fs.read_file(path).unwrap_or_else(|err| cache_io_error_handler(path, err));
+        let size = content.len() as u64;
+        let hash = fnv1a_hash(&content);
+
+        // only the timestamp changed
+        if let Some(previous) = previous.filter(|previous| previous.hash == hash) {
+
+            return Some(CachedIgnoreFile{mtime, size, ..previous});
+        }
+
+        // the patterns come from the content that was hashed, a later change of the file is seen by the next run
+        let file_lines = absolute_ignore::decode_ignore_file(&content);
+        let lines = absolute_ignore::matcher_lines(&file_lines);
+
+        let patterns = match self.keep_comments {
+
+            true => absolute_ignore::annotated_patterns_from_lines(path, &file_lines, dir, root),
+            false => absolute_ignore::patterns_from_lines(path, &file_lines, dir)
+        };
+
+        return Some(CachedIgnoreFile{mtime, size, hash, lines, patterns});
+    }
+}
+
+#[test]
+fn test_cache_invalidation() {
+
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
+    use crate::job::FlattenOptions;
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["build/"]))
+        .add_dir(Dir::new("a")
+            .add_file(File::new_gitignore(&["*.log"]))
+            .add_dir(Dir::new("b")))
+        .add_dir(Dir::new("build")
+            .add_file(File::new_gitignore(&["hidden"])))
+        .add_dir(Dir::new("c")
+            .add_file(File::new_gitignore(&["foo"])));
+
+    let fs = TmpFilesystem::new(&fs_template);
+    let root = fs.path().join("dir");
+    let cache_path = fs.path().join("cache.json");
+
+    // entries modified right before a run are never trusted, see "RACY_MARGIN"
+    fn backdate(path: &Path) {
+
+        let time = SystemTime::now() - Duration::from_secs(3600);
+        std::fs::File::open(path).unwrap().set_modified(time).unwrap();
+
+        if path.is_dir() {
+
+            std::fs::read_dir(path).unwrap().for_each(|entry| backdate(&entry.unwrap().path()));
+        }
+    }
+
+    backdate(&root);
+
+    let run = || {
+
+        let mut cache = FlattenCache::load(&cache_path, &root, ".gitignore", false);
+        let patterns = job::flatten(&crate::file_system::OsFileSystem, &root, ".gitignore", FlattenOptions{cache: Some(&mut cache), ..Default::default()});
+
+        assert_eq!(patterns, job::flatten(&crate::file_system::OsFileSystem, &root, ".gitignore", FlattenOptions::default()));
+
+        let mut read_dirs = cache.read_dirs.clone();
+        let mut read_ignore_files = cache.read_ignore_files.clone();
+        read_dirs.sort();
+        read_ignore_files.sort();
+
+        cache.save(&cache_path).unwrap();
+
+        return (read_dirs, read_ignore_files);
+    };
+
+    // entries modified during a run are read again by the next one
+    let settle = || {
+
+        backdate(&root);
+        run();
+    };
+
+    // empty cache
+    assert_eq!(run(), (
+        vec![root.clone(), root.join("a"), root.join("a/b"), root.join("c")],
+        vec![root.join(".gitignore"), root.join("a/.gitignore"), root.join("c/.gitignore")]
+    ));
+
+    // unchanged tree
+    assert_eq!(run(), (vec![], vec![]));
+
+    // a cache written for another root is discarded, relative roots are resolved from the working directory
+    assert!(FlattenCache::load(&cache_path, &root, ".gitignore", false).previous_entries.is_empty() == false);
+    assert!(FlattenCache::load(&cache_path, &fs.path().join("other"), ".gitignore", false).previous_entries.is_empty());
+    assert!(FlattenCache::load(&cache_path, Path::new("dir"), ".gitignore", false).previous_entries.is_empty());
+
+    // changed ignore file and new directory
+    std::fs::write(root.join("a/.gitignore"), "*.tmp\n").unwrap();
+    std::fs::create_dir(root.join("c/d")).unwrap();
+
+    assert_eq!(run(), (vec![root.join("c"), root.join("c/d")], vec![root.join("a/.gitignore")]));
+    settle();
+
+    // a directory that becomes visible is read, the rest is reused
+    std::fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+
+    assert_eq!(run(), (vec![root.join("build")], vec![root.join(".gitignore"), root.join("build/.gitignore")]));
+    settle();
+
+    // removed directory
+    std::fs::remove_dir(root.join("c/d")).unwrap();
+
+    assert_eq!(run(), (vec![root.join("c")], vec![]));
+}
+
+#[test]
+fn test_cache_without_stamps() {
+
+    use crate::file_system::MemoryFileSystem;
+    use crate::filesystem::template::{Dir, File};
+    use crate::job::FlattenOptions;
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["build/"]))
+        .add_dir(Dir::new("a")
+            .add_file(File::new_gitignore(&["*.log"])))
+        .add_dir(Dir::new("build"));
+
+    let fs = MemoryFileSystem::from_template(Path::new("/"), &fs_template);
+    let root = Path::new("/dir");
+    let cache_dir = crate::filesystem::tmp_filesystem::TmpFilesystem::new(&Dir::new("cache"));
+    let cache_path = cache_dir.path().join("cache.json");
+
+    let expected = job::flatten(&fs, root, ".gitignore", FlattenOptions::default());
+
+    // the tree is read through the given file system, and entries without a timestamp are read again on every run
+    for _ in 0..2 {
+
+        let mut cache = FlattenCache::load(&cache_path, root, ".gitignore", false);
+        assert_eq!(job::flatten(&fs, root, ".gitignore", FlattenOptions{cache: Some(&mut cache), ..Default::default()}), expected);
+
+        assert_eq!(cache.read_dirs, [root.to_owned(), root.join("a")]);
+        assert_eq!(cache.read_ignore_files, [root.join(".gitignore"), root.join("a/.gitignore")]);
+
+        cache.save(&cache_path).unwrap();
+    }
+}
+
+
+fn cache_io_error_handler(path: &Path, err: std::io::Error) -> ! {
+
+    tracing::error!(path = %path.display(), error = ?err, "filesystem traversal IO error");
+    std::process::exit(1);
+}
+
+
+// FNV-1a, unlike "DefaultHasher" it's stable across Rust releases
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+
+    for byte in bytes {
+
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+
+    return hash;
+}
+
+#[test]
+fn test_fnv1a_hash() {
+
+    assert_eq!(fnv1a_hash(b""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(fnv1a_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+    assert_eq!(fnv1a_hash(b"foobar"), 0x8594_4171_f739_67e8);
+}
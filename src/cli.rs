@@ -17,19 +17,153 @@ pub fn build_cli_parser() -> clap::Command {
             .short('n')
             .action(ArgAction::Set)
             .default_value(".gitignore")
+            .global(true)
             .id("file_list_name"));
 
+    // logging
+    let command = command
+        .arg(Arg::new("verbose")
+            .short('v')
+            .long("verbose")
+            .help("log the loaded ignore files and pruned directories, twice to log every visited directory")
+            .action(ArgAction::Count)
+            .global(true)
+            .id("verbose"))
+        .arg(Arg::new("quiet")
+            .short('q')
+            .long("quiet")
+            .help("only log errors")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("verbose")
+            .global(true)
+            .id("quiet"))
+        .arg(Arg::new("log format")
+            .long("log-format")
+            .help("format of the log events written to stderr")
+            .action(ArgAction::Set)
+            .value_parser(["text", "json"])
+            .default_value("text")
+            .global(true)
+            .id("log_format"));
+
     // search path
     let command = command
         .arg(Arg::new("path")
             .action(ArgAction::Set)
+            .value_parser(clap::value_parser!(std::path::PathBuf))
             .default_value(".")
+            .help("directory to flatten, or a .tar, .tar.gz, .tgz or .zip archive flattened as if unpacked in the working directory")
             .id("path"));
+
+    // managed block output
+    let command = command
+        .arg(Arg::new("update block")
+            .long("update-block")
+            .value_name("FILE")
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .help("rewrite only the block between the \"# BEGIN flf\" and \"# END flf\" markers of FILE")
+            .action(ArgAction::Set)
+            .id("update_block"));
+
+    // comments and output order
+    let command = build_output_arguments(command);
+
+    // non-UTF-8 output handling
+    let command = command
+        .arg(Arg::new("non utf8")
+            .long("non-utf8")
+            .help("how patterns that aren't valid UTF-8 are written, \"escape\" output is for display only")
+            .action(ArgAction::Set)
+            .value_parser(["raw", "skip", "escape"])
+            .default_value("raw")
+            .id("non_utf8"));
+
+    // incremental cache
+    let command = command
+        .arg(Arg::new("cache")
+            .long("cache")
+            .value_name("PATH")
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .help("reuse the unchanged directory listings and ignore files of the previous run, stored in PATH")
+            .action(ArgAction::Set)
+            .overrides_with("no_cache")
+            .id("cache"))
+        .arg(Arg::new("no cache")
+            .long("no-cache")
+            .help("walk the whole tree without reading or writing a cache")
+            .action(ArgAction::SetTrue)
+            .overrides_with("cache")
+            .id("no_cache"));
+
+    // known ignore files
+    let command = command
+        .arg(Arg::new("files from")
+            .long("files-from")
+            .value_name("FILE")
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .help("flatten the ignore files listed in FILE, one per line and relative to the search path, instead of walking the tree, \"-\" reads from stdin")
+            .action(ArgAction::Set)
+            .conflicts_with("cache")
+            .id("files_from"))
+        .arg(Arg::new("tracked only")
+            .long("tracked-only")
+            .help("flatten only the ignore files tracked in the git index, untracked ones are left out")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["cache", "files_from"])
+            .id("tracked_only"));
+
+    // git revision source
+    let command = command
+        .arg(Arg::new("rev")
+            .long("rev")
+            .value_name("TREE-ISH")
+            .help("read the directories and ignore files from a git revision instead of the working tree")
+            .action(ArgAction::Set)
+            .conflicts_with_all(["cache", "files_from", "tracked_only"])
+            .id("rev"));
+
+    // command line patterns
+    let command = build_rule_arguments(command);
+
+    // pattern prefixes
+    let command = command
+        .arg(Arg::new("paths")
+            .long("paths")
+            .help("prefix the patterns with the search path as given or made absolute, defaults to relative")
+            .action(ArgAction::Set)
+            .value_parser(["relative", "absolute"])
+            .id("paths"));
+
+    // traversal statistics
+    let command = command
+        .arg(Arg::new("stats")
+            .long("stats")
+            .value_name("FORMAT")
+            .help("print the traversal statistics to stderr, as text or JSON")
+            .action(ArgAction::Set)
+            .num_args(0..=1)
+            .require_equals(true)
+            .value_parser(["text", "json"])
+            .default_missing_value("text")
+            .id("stats"));
+
+    // subcommands
+    let command = command
+        .subcommand(build_explain_subcommand())
+        .subcommand(build_ls_subcommand())
+        .subcommand(build_lint_subcommand())
+        .subcommand(build_watch_subcommand())
+        .subcommand(build_pack_subcommand())
+        .subcommand(build_copy_subcommand())
+        .subcommand(build_diff_subcommand())
+        .subcommand(build_completions_subcommand())
+        .subcommand(build_man_subcommand());
         
     return command;
 }
 
 #[test]
+#[allow(clippy::redundant_pattern_matching)]
 fn test_cli_parser() {
     
     let parser = build_cli_parser();
@@ -46,24 +180,414 @@ fn test_cli_parser() {
     expect_parsing_error("flf -n");
     expect_parsing_error("flf -n foo bar .");
     expect_parsing_error("flf . -n");
+
+    expect_parsing_success("flf --update-block foo");
+    expect_parsing_success("flf . --update-block foo");
+    expect_parsing_error("flf --update-block");
+
+    expect_parsing_success("flf --files-from list");
+    expect_parsing_success("flf --files-from - dir");
+    expect_parsing_error("flf --files-from");
+    expect_parsing_error("flf --files-from list --cache foo");
+    expect_parsing_success("flf --tracked-only");
+    expect_parsing_success("flf --tracked-only dir");
+    expect_parsing_error("flf --tracked-only --files-from list");
+    expect_parsing_success("flf --rev main~3");
+    expect_parsing_success("flf --rev HEAD dir");
+    expect_parsing_error("flf --rev");
+    expect_parsing_error("flf --rev HEAD --cache foo");
+
+    expect_parsing_success("flf explain foo");
+    expect_parsing_success("flf explain foo bar");
+    expect_parsing_success("flf explain --root dir foo");
+    expect_parsing_success("flf -n foo explain bar");
+    expect_parsing_success("flf explain -n foo bar");
+    expect_parsing_error("flf explain");
+    expect_parsing_error("flf explain --root");
+
+    expect_parsing_success("flf ls");
+    expect_parsing_success("flf ls dir");
+    expect_parsing_success("flf ls --ignored");
+    expect_parsing_success("flf ls --included --count --du dir");
+    expect_parsing_error("flf ls --ignored --included");
+    expect_parsing_error("flf ls foo bar");
+
+    expect_parsing_success("flf lint");
+    expect_parsing_success("flf lint dir");
+    expect_parsing_success("flf lint --format json dir");
+    expect_parsing_error("flf lint --format xml");
+
+    expect_parsing_success("flf watch --output out");
+    expect_parsing_success("flf watch -o out --order path --keep-comments dir");
+    expect_parsing_error("flf watch");
+    expect_parsing_error("flf watch -o");
+
+    expect_parsing_success("flf pack --out dist.tar.gz");
+    expect_parsing_success("flf pack -o dist.zip dir");
+    expect_parsing_error("flf pack");
+    expect_parsing_success("flf copy dest");
+    expect_parsing_success("flf copy dest dir");
+    expect_parsing_error("flf copy");
+
+    expect_parsing_success("flf diff old new");
+    expect_parsing_success("flf -n .ignore diff old new");
+    expect_parsing_error("flf diff old");
+
+    expect_parsing_success("flf completions bash");
+    expect_parsing_error("flf completions");
+    expect_parsing_error("flf completions powershell");
+    expect_parsing_success("flf man");
+    expect_parsing_success("flf man -o dir");
+    expect_parsing_error("flf man -o");
+
+    expect_parsing_success("flf -vv --log-format json dir");
+    expect_parsing_success("flf lint -q dir");
+    expect_parsing_error("flf -q -v");
+
+    expect_parsing_success("flf --paths absolute dir");
+    expect_parsing_error("flf --paths sideways");
+
+    expect_parsing_success("flf pack --exclude target/ -o dist.tar.gz");
+    expect_parsing_success("flf ls --include keep.log --extra-file extra.ignore dir");
+    expect_parsing_error("flf completions bash --exclude target/");
+}
+
+
+// patterns applied to the whole tree, shared by the commands that traverse one
+fn build_rule_arguments(command: clap::Command) -> clap::Command {
+
+    use clap::{Arg, ArgAction};
+
+    let command = command
+        .arg(Arg::new("exclude")
+            .long("exclude")
+            .value_name("PATTERN")
+            .value_parser(parse_pattern_argument)
+            .help("ignore the paths matching PATTERN, with a lower precedence than the ignore files of the tree")
+            .action(ArgAction::Append)
+            .id("exclude"))
+        .arg(Arg::new("include")
+            .long("include")
+            .value_name("PATTERN")
+            .value_parser(parse_pattern_argument)
+            .help("re-include the paths matching PATTERN, excluded by the configuration or an earlier --exclude")
+            .action(ArgAction::Append)
+            .id("include"))
+        .arg(Arg::new("extra file")
+            .long("extra-file")
+            .value_name("FILE")
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .help("apply the patterns of FILE to the whole tree, as if it were an ignore file above the search path")
+            .action(ArgAction::Append)
+            .id("extra_file"));
+
+    return command;
+}
+
+
+// layout of the flattened output, shared by the commands that write one so the configuration overrides them the same way
+fn build_output_arguments(command: clap::Command) -> clap::Command {
+
+    use clap::{Arg, ArgAction};
+
+    // comments
+    let command = command
+        .arg(Arg::new("keep comments")
+            .long("keep-comments")
+            .help("keep the comments and blank lines of the ignore files, with a header comment per source file")
+            .action(ArgAction::SetTrue)
+            .overrides_with("no_keep_comments")
+            .id("keep_comments"))
+        .arg(Arg::new("no keep comments")
+            .long("no-keep-comments")
+            .help("drop the comments and blank lines of the ignore files, even if the configuration keeps them")
+            .action(ArgAction::SetTrue)
+            .overrides_with("keep_comments")
+            .id("no_keep_comments"));
+
+    // output order
+    let command = command
+        .arg(Arg::new("order")
+            .long("order")
+            .help("order of the ignore file sections, patterns keep their order within each file")
+            .action(ArgAction::Set)
+            .value_parser(["traversal", "path", "depth-first", "breadth-first"])
+            .default_value("depth-first")
+            .id("order"));
+
+    return command;
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct LogArguments {
+
+    pub level: tracing::level_filters::LevelFilter,
+    pub format: crate::logging::LogFormat
 }
 
+// shared by every subcommand
+pub fn parse_log_matches(matches: &clap::ArgMatches) -> LogArguments {
+
+    use crate::logging::{self, LogFormat};
+
+    let verbosity = matches.get_count("verbose");
+    let quiet = matches.get_flag("quiet");
+
+    let format = match matches.get_one::<String>("log_format").expect("invalid matches").as_str() {
+
+        "text" => LogFormat::Text,
+        "json" => LogFormat::Json,
+        _ => panic!("invalid log format")
+    };
+
+    return LogArguments{level: logging::level_filter(verbosity, quiet), format};
+}
+
+#[test]
+fn test_log_matches_parser() {
+
+    use tracing::level_filters::LevelFilter;
+    use crate::logging::LogFormat;
+
+    let parser = build_cli_parser();
+
+    let parse = |args: &str| parse_log_matches(&parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments"));
+
+    assert_eq!(parse("flf"), LogArguments{level: LevelFilter::WARN, format: LogFormat::Text});
+    assert_eq!(parse("flf -v dir").level, LevelFilter::INFO);
+    assert_eq!(parse("flf -vv").level, LevelFilter::DEBUG);
+    assert_eq!(parse("flf -v --verbose -v").level, LevelFilter::TRACE);
+    assert_eq!(parse("flf -q").level, LevelFilter::ERROR);
+    assert_eq!(parse("flf --log-format json").format, LogFormat::Json);
+
+    // global arguments given after a subcommand
+    assert_eq!(parse("flf ls -v --log-format json"), LogArguments{level: LevelFilter::INFO, format: LogFormat::Json});
+
+    assert!(parser.clone().try_get_matches_from(["flf", "-q", "-v"]).is_err());
+    assert!(parser.clone().try_get_matches_from(["flf", "--log-format", "xml"]).is_err());
+}
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Arguments {
 
     pub path: std::path::PathBuf,
-    pub ignore_file_name: String
+    pub ignore_file_name: String,
+    pub update_block: Option<std::path::PathBuf>,
+    pub keep_comments: bool,
+    pub non_utf8_mode: crate::output::NonUtf8Mode,
+    pub order: crate::job::OutputOrder,
+    pub cache: Option<std::path::PathBuf>,
+    pub files_from: Option<std::path::PathBuf>,
+    pub tracked_only: bool,
+    pub rev: Option<String>,
+    pub stats: Option<crate::stats::StatsFormat>,
+    pub rules: RuleArguments
+}
+
+// patterns applied under the root of every traversed tree
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RuleArguments {
+
+    // from the configuration file, named as the source of the global patterns
+    pub excluded_dirs: Vec<String>,
+    pub global_patterns: Vec<String>,
+    pub config_path: Option<std::path::PathBuf>,
+
+    // "--exclude" and "--include" patterns in command line order, the included ones are negated
+    pub extra_patterns: Vec<String>,
+    pub extra_files: Vec<std::path::PathBuf>
+}
+
+fn parse_rule_arguments(matches: &clap::ArgMatches, config: &crate::config::Config) -> RuleArguments {
+
+    let mut indexed_patterns = Vec::new();
+
+    for (id, prefix) in [("exclude", ""), ("include", "!")] {
+
+        if let (Some(indices), Some(values)) = (matches.indices_of(id), matches.get_many::<String>(id)) {
+
+            indexed_patterns.extend(indices.zip(values).map(|(idx, value)| (idx, format!("{}{}", prefix, value))));
+        }
+    }
+
+    indexed_patterns.sort_by_key(|(idx, _)| *idx);
+
+    let extra_patterns = indexed_patterns.into_iter().map(|(_, pattern)| pattern).collect();
+    let extra_files = matches.get_many::<std::path::PathBuf>("extra_file").map_or_else(Vec::new, |files| files.cloned().collect());
+
+    return RuleArguments{
+        excluded_dirs: config.exclude_dirs.clone(),
+        global_patterns: config.global_patterns.clone(),
+        config_path: config.path.clone(),
+        extra_patterns,
+        extra_files
+    };
+}
+
+// the start path of the configuration file search, none for the commands that don't read a tree
+pub fn parse_start_path(matches: &clap::ArgMatches) -> Option<std::path::PathBuf> {
+
+    let get_path = |matches: &clap::ArgMatches, id: &str| matches.get_one::<std::path::PathBuf>(id).expect("invalid matches").to_owned();
+
+    return match matches.subcommand() {
+
+        Some(("explain", matches)) => Some(get_path(matches, "root")),
+        Some(("ls" | "lint" | "watch" | "pack" | "copy", matches)) => Some(get_path(matches, "path")),
+
+        // the rules of the new tree judge both of them
+        Some(("diff", matches)) => Some(get_path(matches, "new_root")),
+        Some(_) => None,
+        None => Some(get_path(matches, "path"))
+    };
+}
+
+#[test]
+fn test_start_path_parser() {
+
+    let parser = build_cli_parser();
+
+    let parse = |args: &str| parse_start_path(&parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments"));
+
+    assert_eq!(parse("flf"), Some(std::path::PathBuf::from(".")));
+    assert_eq!(parse("flf dir"), Some(std::path::PathBuf::from("dir")));
+    assert_eq!(parse("flf explain --root dir foo"), Some(std::path::PathBuf::from("dir")));
+    assert_eq!(parse("flf ls dir"), Some(std::path::PathBuf::from("dir")));
+    assert_eq!(parse("flf lint"), Some(std::path::PathBuf::from(".")));
+    assert_eq!(parse("flf watch -o out dir"), Some(std::path::PathBuf::from("dir")));
+    assert_eq!(parse("flf pack -o dist.zip dir"), Some(std::path::PathBuf::from("dir")));
+    assert_eq!(parse("flf copy dest dir"), Some(std::path::PathBuf::from("dir")));
+    assert_eq!(parse("flf diff old new"), Some(std::path::PathBuf::from("new")));
+    assert_eq!(parse("flf completions bash"), None);
+    assert_eq!(parse("flf man"), None);
+}
+
+// values given on the command line, the defaults are left to the configuration
+fn get_explicit_value(matches: &clap::ArgMatches, id: &str) -> Option<String> {
+
+    return match matches.value_source(id) {
+
+        Some(clap::parser::ValueSource::DefaultValue) | None => None,
+        Some(_) => matches.get_one::<String>(id).cloned()
+    };
+}
+
+fn parse_ignore_file_name(matches: &clap::ArgMatches, config: &crate::config::Config) -> String {
+
+    return get_explicit_value(matches, "file_list_name").or_else(|| config.ignore_file_name.clone())
+        .unwrap_or_else(|| matches.get_one::<String>("file_list_name").expect("invalid matches").to_owned());
+}
+
+// "--keep-comments" and "--no-keep-comments" override each other, the last one wins
+fn parse_keep_comments(matches: &clap::ArgMatches, config: &crate::config::Config) -> bool {
+
+    return match (matches.get_flag("keep_comments"), matches.get_flag("no_keep_comments")) {
+
+        (true, _) => true,
+        (_, true) => false,
+        _ => config.keep_comments.unwrap_or(false)
+    };
+}
+
+fn parse_order(matches: &clap::ArgMatches, config: &crate::config::Config) -> crate::job::OutputOrder {
+
+    return match get_explicit_value(matches, "order") {
+
+        Some(value) => parse_output_order(&value),
+        None => config.order.unwrap_or_else(|| parse_output_order(matches.get_one::<String>("order").expect("invalid matches")))
+    };
 }
 
-pub fn parse_cli_matches(matches: &clap::ArgMatches) -> Arguments {
+// the command line takes precedence over the configuration, which takes precedence over the defaults
+// fails when the search path has to be made absolute and can't be
+pub fn parse_cli_matches(matches: &clap::ArgMatches, config: &crate::config::Config) -> std::io::Result<Arguments> {
+
+    use crate::config::PathMode;
 
     let get_value = |id: &str| matches.get_one::<String>(id).expect("invalid matches").to_owned();
 
-    let path = std::path::PathBuf::from(get_value("path"));
-    let ignore_file_name = get_value("file_list_name");
+    let path_mode = match matches.get_one::<String>("paths").map(String::as_str) {
 
-    return Arguments{path, ignore_file_name};
+        Some("relative") => PathMode::Relative,
+        Some("absolute") => PathMode::Absolute,
+        Some(_) => panic!("invalid path mode"),
+        None => config.paths.unwrap_or(PathMode::Relative)
+    };
+
+    let path = matches.get_one::<std::path::PathBuf>("path").expect("invalid matches").to_owned();
+
+    let path = match path_mode {
+
+        PathMode::Relative => path,
+        PathMode::Absolute => std::path::absolute(path)?
+    };
+
+    let ignore_file_name = parse_ignore_file_name(matches, config);
+    let update_block = matches.get_one::<std::path::PathBuf>("update_block").cloned();
+    let keep_comments = parse_keep_comments(matches, config);
+
+    let non_utf8_mode = match get_explicit_value(matches, "non_utf8") {
+
+        Some(value) => parse_non_utf8_mode(&value),
+        None => config.non_utf8.unwrap_or_else(|| parse_non_utf8_mode(&get_value("non_utf8")))
+    };
+
+    let order = parse_order(matches, config);
+
+    let files_from = matches.get_one::<std::path::PathBuf>("files_from").cloned();
+    let tracked_only = matches.get_flag("tracked_only");
+    let rev = matches.get_one::<String>("rev").cloned();
+    let stats = matches.get_one::<String>("stats").map(|value| parse_stats_format(value));
+
+    // the configured cache only applies to the directory walk, where "--cache" is accepted
+    let config_cache_applies = files_from.is_none() && tracked_only == false && rev.is_none() && crate::archive::ArchiveFormat::from_path(&path).is_none();
+
+    let cache = match matches.get_flag("no_cache") {
+
+        true => None,
+        false => matches.get_one::<std::path::PathBuf>("cache").cloned().or_else(|| config.cache.clone().filter(|_| config_cache_applies))
+    };
+
+    let rules = parse_rule_arguments(matches, config);
+
+    return Ok(Arguments{path, ignore_file_name, update_block, keep_comments, non_utf8_mode, order, cache, files_from, tracked_only, rev, stats, rules});
+}
+
+// validated as a line of an ignore file would be, "--include" values are negated afterwards
+fn parse_pattern_argument(value: &str) -> Result<String, String> {
+
+    use crate::absolute_ignore::{filter_ignore_line, parse_ignore_pattern, strip_trailing_whitespaces};
+
+    if filter_ignore_line(value) == false || parse_ignore_pattern(strip_trailing_whitespaces(value)).is_none() {
+
+        return Err(format!("\"{}\" isn't a gitignore pattern", value));
+    }
+
+    return Ok(value.to_owned());
+}
+
+#[test]
+fn test_cli_extra_patterns() {
+
+    let parser = build_cli_parser();
+
+    let parse = |args: &str| parse_cli_matches(&parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments"), &crate::config::Config::default()).expect("invalid search path");
+
+    assert_eq!(parse("flf").rules.extra_patterns, Vec::<String>::new());
+    assert_eq!(parse("flf --exclude target/ dir").rules.extra_patterns, ["target/"]);
+    assert_eq!(parse("flf --exclude *.log --include keep.log --exclude keep.log.* --include /a/").rules.extra_patterns, ["*.log", "!keep.log", "keep.log.*", "!/a/"]);
+    assert_eq!(parse("flf --extra-file a --extra-file b").rules.extra_files, [std::path::PathBuf::from("a"), std::path::PathBuf::from("b")]);
+
+    // the subcommands traversing a tree take them as well
+    let matches = parser.clone().try_get_matches_from("flf pack --exclude target/ -o dist.tar.gz --include target/keep --extra-file a".split_ascii_whitespace()).expect("invalid arguments");
+    let rules = parse_pack_matches(matches.subcommand_matches("pack").unwrap(), &crate::config::Config::default()).rules;
+    assert_eq!(rules.extra_patterns, ["target/", "!target/keep"]);
+    assert_eq!(rules.extra_files, [std::path::PathBuf::from("a")]);
+
+    for args in [vec!["flf", "--exclude", ""], vec!["flf", "--exclude", "# comment"], vec!["flf", "--include", "/"], vec!["flf", "--exclude"]] {
+
+        assert!(parser.clone().try_get_matches_from(args).is_err());
+    }
 }
 
 #[test]
@@ -73,10 +597,10 @@ fn test_cli_matches_parser() {
 
     let expect_result = |args: &str, path: &str, ignore_file_name: &str| {
         
-        let expected_arguments = Arguments{path: std::path::PathBuf::from(path), ignore_file_name: ignore_file_name.to_owned()};
+        let expected_arguments = Arguments{path: std::path::PathBuf::from(path), ignore_file_name: ignore_file_name.to_owned(), update_block: None, keep_comments: false, non_utf8_mode: crate::output::NonUtf8Mode::Raw, order: crate::job::OutputOrder::DepthFirst, cache: None, files_from: None, tracked_only: false, rev: None, stats: None, rules: RuleArguments::default()};
         let matches = parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments");
 
-        assert_eq!(parse_cli_matches(&matches), expected_arguments)
+        assert_eq!(parse_cli_matches(&matches, &crate::config::Config::default()).expect("invalid search path"), expected_arguments)
     };
 
     expect_result("flf", ".", ".gitignore");
@@ -86,3 +610,959 @@ fn test_cli_matches_parser() {
     expect_result("flf -n bar foo", "foo", "bar");
     expect_result("flf foo -n bar", "foo", "bar");
 }
+
+#[test]
+fn test_cli_update_block() {
+
+    let parser = build_cli_parser();
+
+    let parse = |args: &str| parse_cli_matches(&parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments"), &crate::config::Config::default()).expect("invalid search path");
+
+    assert_eq!(parse("flf").update_block, None);
+    assert_eq!(parse("flf --update-block foo").update_block, Some(std::path::PathBuf::from("foo")));
+    assert_eq!(parse("flf bar --update-block foo").path, std::path::PathBuf::from("bar"));
+}
+
+#[test]
+fn test_cli_keep_comments() {
+
+    let parser = build_cli_parser();
+
+    let parse = |args: &str| parse_cli_matches(&parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments"), &crate::config::Config::default()).expect("invalid search path");
+
+    assert_eq!(parse("flf").keep_comments, false);
+    assert_eq!(parse("flf --keep-comments").keep_comments, true);
+    assert_eq!(parse("flf --no-keep-comments").keep_comments, false);
+    assert_eq!(parse("flf --keep-comments --no-keep-comments").keep_comments, false);
+    assert_eq!(parse("flf --no-keep-comments --keep-comments").keep_comments, true);
+    assert_eq!(parse("flf dir --keep-comments").path, std::path::PathBuf::from("dir"));
+}
+
+fn parse_non_utf8_mode(value: &str) -> crate::output::NonUtf8Mode {
+
+    use crate::output::NonUtf8Mode;
+
+    return match value {
+
+        "raw" => NonUtf8Mode::Raw,
+        "skip" => NonUtf8Mode::Skip,
+        "escape" => NonUtf8Mode::Escape,
+        _ => panic!("invalid non-UTF-8 mode")
+    };
+}
+
+#[test]
+fn test_cli_non_utf8_mode() {
+
+    use crate::output::NonUtf8Mode;
+
+    let parser = build_cli_parser();
+
+    let parse = |args: &str| parse_cli_matches(&parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments"), &crate::config::Config::default()).expect("invalid search path");
+
+    assert_eq!(parse("flf").non_utf8_mode, NonUtf8Mode::Raw);
+    assert_eq!(parse("flf --non-utf8 raw").non_utf8_mode, NonUtf8Mode::Raw);
+    assert_eq!(parse("flf --non-utf8 skip").non_utf8_mode, NonUtf8Mode::Skip);
+    assert_eq!(parse("flf --non-utf8 escape dir").non_utf8_mode, NonUtf8Mode::Escape);
+    assert!(parser.clone().try_get_matches_from(["flf", "--non-utf8", "foo"]).is_err());
+}
+
+#[test]
+fn test_cli_cache() {
+
+    let parser = build_cli_parser();
+
+    let parse = |args: &str| parse_cli_matches(&parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments"), &crate::config::Config::default()).expect("invalid search path");
+
+    assert_eq!(parse("flf").cache, None);
+    assert_eq!(parse("flf --cache foo").cache, Some(std::path::PathBuf::from("foo")));
+    assert_eq!(parse("flf --cache foo dir").path, std::path::PathBuf::from("dir"));
+    assert_eq!(parse("flf --no-cache").cache, None);
+    assert_eq!(parse("flf --cache foo --no-cache").cache, None);
+    assert_eq!(parse("flf --no-cache --cache foo").cache, Some(std::path::PathBuf::from("foo")));
+    assert!(parser.clone().try_get_matches_from(["flf", "--cache"]).is_err());
+}
+
+#[test]
+fn test_cli_config() {
+
+    use crate::config::{Config, PathMode};
+    use crate::job::OutputOrder;
+    use crate::output::NonUtf8Mode;
+
+    let parser = build_cli_parser();
+
+    let parse = |args: &str, config: &Config| parse_cli_matches(&parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments"), config).expect("invalid search path");
+
+    let config = Config{
+        ignore_file_name: Some(".ignore".to_owned()),
+        keep_comments: Some(true),
+        non_utf8: Some(NonUtf8Mode::Skip),
+        order: Some(OutputOrder::Path),
+        paths: Some(PathMode::Absolute),
+        cache: Some(std::path::PathBuf::from("/project/flf-cache")),
+        exclude_dirs: vec!["node_modules".to_owned()],
+        global_patterns: vec!["*.swp".to_owned()],
+        path: Some(std::path::PathBuf::from("/project/flf.toml"))
+    };
+
+    let args = parse("flf dir", &config);
+    assert_eq!(args.ignore_file_name, ".ignore");
+    assert_eq!(args.keep_comments, true);
+    assert_eq!(args.non_utf8_mode, NonUtf8Mode::Skip);
+    assert_eq!(args.order, OutputOrder::Path);
+    assert_eq!(args.path, std::env::current_dir().unwrap().join("dir"));
+    assert_eq!(args.cache, Some(std::path::PathBuf::from("/project/flf-cache")));
+    assert_eq!(args.rules, RuleArguments{excluded_dirs: vec!["node_modules".to_owned()], global_patterns: vec!["*.swp".to_owned()], config_path: Some(std::path::PathBuf::from("/project/flf.toml")), ..RuleArguments::default()});
+
+    // the command line wins, even when it repeats a default value
+    let args = parse("flf -n .gitignore --non-utf8 raw --order depth-first --paths relative dir", &config);
+    assert_eq!(args.ignore_file_name, ".gitignore");
+    assert_eq!(args.non_utf8_mode, NonUtf8Mode::Raw);
+    assert_eq!(args.order, OutputOrder::DepthFirst);
+    assert_eq!(args.path, std::path::PathBuf::from("dir"));
+    assert_eq!(parse("flf --no-keep-comments", &config).keep_comments, false);
+
+    assert_eq!(parse("flf --no-cache", &config).cache, None);
+    assert_eq!(parse("flf --cache foo", &config).cache, Some(std::path::PathBuf::from("foo")));
+    assert_eq!(parse("flf --rev HEAD", &config).cache, None);
+    assert_eq!(parse("flf --stats", &config).cache, Some(std::path::PathBuf::from("/project/flf-cache")));
+    assert_eq!(parse("flf dist.tar.gz", &config).cache, None);
+}
+
+#[test]
+fn test_subcommand_config() {
+
+    use crate::config::Config;
+    use crate::job::OutputOrder;
+
+    let parser = build_cli_parser();
+
+    let get_matches = |args: &str| parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments");
+
+    let config = Config{
+        ignore_file_name: Some(".ignore".to_owned()),
+        keep_comments: Some(true),
+        order: Some(OutputOrder::Path),
+        exclude_dirs: vec!["node_modules".to_owned()],
+        global_patterns: vec!["*.swp".to_owned()],
+        path: Some(std::path::PathBuf::from("/project/flf.toml")),
+        ..Config::default()
+    };
+
+    let rules = RuleArguments{excluded_dirs: vec!["node_modules".to_owned()], global_patterns: vec!["*.swp".to_owned()], config_path: Some(std::path::PathBuf::from("/project/flf.toml")), ..RuleArguments::default()};
+
+    let matches = get_matches("flf explain foo");
+    let args = parse_explain_matches(matches.subcommand_matches("explain").unwrap(), &config);
+    assert_eq!((args.ignore_file_name.as_str(), &args.rules), (".ignore", &rules));
+
+    let matches = get_matches("flf ls");
+    let args = parse_ls_matches(matches.subcommand_matches("ls").unwrap(), &config);
+    assert_eq!((args.ignore_file_name.as_str(), &args.rules), (".ignore", &rules));
+
+    let matches = get_matches("flf lint");
+    let args = parse_lint_matches(matches.subcommand_matches("lint").unwrap(), &config);
+    assert_eq!((args.ignore_file_name.as_str(), &args.rules), (".ignore", &rules));
+
+    let matches = get_matches("flf pack -o dist.zip");
+    let args = parse_pack_matches(matches.subcommand_matches("pack").unwrap(), &config);
+    assert_eq!((args.ignore_file_name.as_str(), &args.rules), (".ignore", &rules));
+
+    let matches = get_matches("flf copy dest");
+    let args = parse_copy_matches(matches.subcommand_matches("copy").unwrap(), &config);
+    assert_eq!((args.ignore_file_name.as_str(), &args.rules), (".ignore", &rules));
+
+    let matches = get_matches("flf diff old new");
+    let args = parse_diff_matches(matches.subcommand_matches("diff").unwrap(), &config);
+    assert_eq!((args.ignore_file_name.as_str(), &args.rules), (".ignore", &rules));
+
+    let matches = get_matches("flf watch -o out");
+    let args = parse_watch_matches(matches.subcommand_matches("watch").unwrap(), &config);
+    assert_eq!((args.ignore_file_name.as_str(), &args.rules), (".ignore", &rules));
+    assert_eq!(args.keep_comments, true);
+    assert_eq!(args.order, OutputOrder::Path);
+
+    // the command line wins, even when it repeats a default value
+    let matches = get_matches("flf -n .gitignore watch -o out --no-keep-comments --order depth-first");
+    let args = parse_watch_matches(matches.subcommand_matches("watch").unwrap(), &config);
+    assert_eq!(args.ignore_file_name, ".gitignore");
+    assert_eq!(args.keep_comments, false);
+    assert_eq!(args.order, OutputOrder::DepthFirst);
+
+    let matches = get_matches("flf ls -n .gitignore");
+    assert_eq!(parse_ls_matches(matches.subcommand_matches("ls").unwrap(), &config).ignore_file_name, ".gitignore");
+}
+
+fn parse_stats_format(value: &str) -> crate::stats::StatsFormat {
+
+    use crate::stats::StatsFormat;
+
+    return match value {
+
+        "text" => StatsFormat::Text,
+        "json" => StatsFormat::Json,
+        _ => panic!("invalid stats format")
+    };
+}
+
+#[test]
+fn test_cli_stats() {
+
+    use crate::stats::StatsFormat;
+
+    let parser = build_cli_parser();
+
+    let parse = |args: &str| parse_cli_matches(&parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments"), &crate::config::Config::default()).expect("invalid search path");
+
+    assert_eq!(parse("flf").stats, None);
+    assert_eq!(parse("flf --stats").stats, Some(StatsFormat::Text));
+    assert_eq!(parse("flf --stats=json").stats, Some(StatsFormat::Json));
+    assert_eq!(parse("flf --stats dir").path, std::path::PathBuf::from("dir"));
+    assert_eq!(parse("flf --stats --rev HEAD").stats, Some(StatsFormat::Text));
+    assert!(parser.clone().try_get_matches_from(["flf", "--stats=xml"]).is_err());
+    assert_eq!(parse("flf --stats --cache foo").stats, Some(StatsFormat::Text));
+    assert_eq!(parse("flf --stats --files-from list").stats, Some(StatsFormat::Text));
+    assert_eq!(parse("flf --stats --tracked-only").stats, Some(StatsFormat::Text));
+}
+
+fn parse_output_order(value: &str) -> crate::job::OutputOrder {
+
+    use crate::job::OutputOrder;
+
+    return match value {
+
+        "traversal" => OutputOrder::Traversal,
+        "path" => OutputOrder::Path,
+        "depth-first" => OutputOrder::DepthFirst,
+        "breadth-first" => OutputOrder::BreadthFirst,
+        _ => panic!("invalid output order")
+    };
+}
+
+#[test]
+fn test_cli_output_order() {
+
+    use crate::job::OutputOrder;
+
+    let parser = build_cli_parser();
+
+    let parse = |args: &str| parse_cli_matches(&parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments"), &crate::config::Config::default()).expect("invalid search path");
+
+    assert_eq!(parse("flf").order, OutputOrder::DepthFirst);
+    assert_eq!(parse("flf --order traversal").order, OutputOrder::Traversal);
+    assert_eq!(parse("flf --order path").order, OutputOrder::Path);
+    assert_eq!(parse("flf --order depth-first").order, OutputOrder::DepthFirst);
+    assert_eq!(parse("flf --order=breadth-first dir").order, OutputOrder::BreadthFirst);
+    assert!(parser.clone().try_get_matches_from(["flf", "--order", "random"]).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cli_non_utf8_path() {
+
+    use std::os::unix::ffi::OsStringExt;
+
+    let path = std::ffi::OsString::from_vec(b"dir_\xff".to_vec());
+    let matches = build_cli_parser().try_get_matches_from([std::ffi::OsString::from("flf"), path.clone()]).expect("invalid arguments");
+
+    assert_eq!(parse_cli_matches(&matches, &crate::config::Config::default()).expect("invalid search path").path.into_os_string(), path);
+
+    // the subcommands take the same paths
+    let get_subcommand_matches = |args: &[&str]| {
+
+        let args = args.iter().map(std::ffi::OsString::from).chain([path.clone()]);
+        return build_cli_parser().try_get_matches_from(args).expect("invalid arguments");
+    };
+
+    let config = crate::config::Config::default();
+
+    let matches = get_subcommand_matches(&["flf", "ls"]);
+    assert_eq!(parse_ls_matches(matches.subcommand_matches("ls").unwrap(), &config).path.into_os_string(), path);
+
+    let matches = get_subcommand_matches(&["flf", "lint"]);
+    assert_eq!(parse_lint_matches(matches.subcommand_matches("lint").unwrap(), &config).path.into_os_string(), path);
+
+    let matches = get_subcommand_matches(&["flf", "explain", "--root", "dir_\u{e9}"]);
+    let args = parse_explain_matches(matches.subcommand_matches("explain").unwrap(), &config);
+    assert_eq!(args.root, std::path::PathBuf::from("dir_\u{e9}"));
+    assert_eq!(args.paths, [std::path::PathBuf::from(path.clone())]);
+
+    let matches = get_subcommand_matches(&["flf", "explain", "foo", "--root"]);
+    assert_eq!(parse_explain_matches(matches.subcommand_matches("explain").unwrap(), &config).root.into_os_string(), path);
+}
+
+
+fn build_explain_subcommand() -> clap::Command {
+
+    use clap::{Command, Arg, ArgAction};
+
+    let command = Command::new("explain")
+        .about("show which pattern decides whether each path is ignored, exits with 1 if no path is ignored");
+
+    // search root
+    let command = command
+        .arg(Arg::new("root")
+            .long("root")
+            .action(ArgAction::Set)
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .default_value(".")
+            .id("root"));
+
+    // paths to explain
+    let command = command
+        .arg(Arg::new("paths")
+            .action(ArgAction::Append)
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .required(true)
+            .num_args(1..)
+            .id("paths"));
+
+    // command line patterns
+    let command = build_rule_arguments(command);
+
+    return command;
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ExplainArguments {
+
+    pub root: std::path::PathBuf,
+    pub paths: Vec<std::path::PathBuf>,
+    pub ignore_file_name: String,
+    pub rules: RuleArguments
+}
+
+pub fn parse_explain_matches(matches: &clap::ArgMatches, config: &crate::config::Config) -> ExplainArguments {
+
+    let root = matches.get_one::<std::path::PathBuf>("root").expect("invalid matches").to_owned();
+    let paths = matches.get_many::<std::path::PathBuf>("paths").expect("invalid matches").cloned().collect();
+    let ignore_file_name = parse_ignore_file_name(matches, config);
+    let rules = parse_rule_arguments(matches, config);
+
+    return ExplainArguments{root, paths, ignore_file_name, rules};
+}
+
+#[test]
+fn test_explain_matches_parser() {
+
+    let parser = build_cli_parser();
+
+    let expect_result = |args: &str, root: &str, paths: &[&str], ignore_file_name: &str| {
+
+        let expected_arguments = ExplainArguments{
+            root: std::path::PathBuf::from(root),
+            paths: paths.iter().map(std::path::PathBuf::from).collect(),
+            ignore_file_name: ignore_file_name.to_owned(),
+            rules: RuleArguments::default()
+        };
+
+        let matches = parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments");
+        let (name, matches) = matches.subcommand().expect("missing subcommand");
+
+        assert_eq!(name, "explain");
+        assert_eq!(parse_explain_matches(matches, &crate::config::Config::default()), expected_arguments)
+    };
+
+    expect_result("flf explain foo", ".", &["foo"], ".gitignore");
+    expect_result("flf explain foo bar", ".", &["foo", "bar"], ".gitignore");
+    expect_result("flf explain --root dir foo", "dir", &["foo"], ".gitignore");
+    expect_result("flf -n baz explain foo", ".", &["foo"], "baz");
+    expect_result("flf explain foo -n baz", ".", &["foo"], "baz");
+}
+
+
+fn build_ls_subcommand() -> clap::Command {
+
+    use clap::{Command, Arg, ArgAction};
+
+    let command = Command::new("ls")
+        .about("list the files and directories included or excluded by the ignore files");
+
+    // selection
+    let command = command
+        .arg(Arg::new("included")
+            .long("included")
+            .help("list the included entries (default)")
+            .action(ArgAction::SetTrue)
+            .id("included"))
+        .arg(Arg::new("ignored")
+            .long("ignored")
+            .help("list the ignored entries")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("included")
+            .id("ignored"));
+
+    // summaries
+    let command = command
+        .arg(Arg::new("count")
+            .long("count")
+            .help("print the file count per top-level directory")
+            .action(ArgAction::SetTrue)
+            .id("count"))
+        .arg(Arg::new("du")
+            .long("du")
+            .help("print the total size per top-level directory")
+            .action(ArgAction::SetTrue)
+            .id("du"));
+
+    // search path
+    let command = command
+        .arg(Arg::new("path")
+            .action(ArgAction::Set)
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .default_value(".")
+            .id("path"));
+
+    // command line patterns
+    let command = build_rule_arguments(command);
+
+    return command;
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ListArguments {
+
+    pub path: std::path::PathBuf,
+    pub ignore_file_name: String,
+    pub rules: RuleArguments,
+    pub selection: crate::listing::Selection,
+    pub count: bool,
+    pub du: bool
+}
+
+pub fn parse_ls_matches(matches: &clap::ArgMatches, config: &crate::config::Config) -> ListArguments {
+
+    use crate::listing::Selection;
+
+    let path = matches.get_one::<std::path::PathBuf>("path").expect("invalid matches").to_owned();
+    let ignore_file_name = parse_ignore_file_name(matches, config);
+    let rules = parse_rule_arguments(matches, config);
+    let selection = if matches.get_flag("ignored") { Selection::Ignored } else { Selection::Included };
+    let count = matches.get_flag("count");
+    let du = matches.get_flag("du");
+
+    return ListArguments{path, ignore_file_name, rules, selection, count, du};
+}
+
+#[test]
+fn test_ls_matches_parser() {
+
+    use crate::listing::Selection;
+
+    let parser = build_cli_parser();
+
+    let expect_result = |args: &str, path: &str, selection: Selection, count: bool, du: bool| {
+
+        let expected_arguments = ListArguments{path: std::path::PathBuf::from(path), ignore_file_name: ".gitignore".to_owned(), rules: RuleArguments::default(), selection, count, du};
+
+        let matches = parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments");
+        let (name, matches) = matches.subcommand().expect("missing subcommand");
+
+        assert_eq!(name, "ls");
+        assert_eq!(parse_ls_matches(matches, &crate::config::Config::default()), expected_arguments)
+    };
+
+    expect_result("flf ls", ".", Selection::Included, false, false);
+    expect_result("flf ls dir", "dir", Selection::Included, false, false);
+    expect_result("flf ls --included", ".", Selection::Included, false, false);
+    expect_result("flf ls --ignored dir", "dir", Selection::Ignored, false, false);
+    expect_result("flf ls --count", ".", Selection::Included, true, false);
+    expect_result("flf ls --ignored --count --du", ".", Selection::Ignored, true, true);
+}
+
+
+fn build_lint_subcommand() -> clap::Command {
+
+    use clap::{Command, Arg, ArgAction};
+
+    let command = Command::new("lint")
+        .about("report common mistakes in the ignore files, exits with 1 if any issue is found");
+
+    // output format
+    let command = command
+        .arg(Arg::new("format")
+            .long("format")
+            .action(ArgAction::Set)
+            .value_parser(["text", "json"])
+            .default_value("text")
+            .id("format"));
+
+    // search path
+    let command = command
+        .arg(Arg::new("path")
+            .action(ArgAction::Set)
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .default_value(".")
+            .id("path"));
+
+    // command line patterns
+    let command = build_rule_arguments(command);
+
+    return command;
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct LintArguments {
+
+    pub path: std::path::PathBuf,
+    pub ignore_file_name: String,
+    pub rules: RuleArguments,
+    pub json: bool
+}
+
+pub fn parse_lint_matches(matches: &clap::ArgMatches, config: &crate::config::Config) -> LintArguments {
+
+    let get_value = |id: &str| matches.get_one::<String>(id).expect("invalid matches").to_owned();
+
+    let path = matches.get_one::<std::path::PathBuf>("path").expect("invalid matches").to_owned();
+    let ignore_file_name = parse_ignore_file_name(matches, config);
+    let rules = parse_rule_arguments(matches, config);
+    let json = get_value("format") == "json";
+
+    return LintArguments{path, ignore_file_name, rules, json};
+}
+
+#[test]
+fn test_lint_matches_parser() {
+
+    let parser = build_cli_parser();
+
+    let expect_result = |args: &str, path: &str, json: bool| {
+
+        let expected_arguments = LintArguments{path: std::path::PathBuf::from(path), ignore_file_name: ".gitignore".to_owned(), rules: RuleArguments::default(), json};
+
+        let matches = parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments");
+        let (name, matches) = matches.subcommand().expect("missing subcommand");
+
+        assert_eq!(name, "lint");
+        assert_eq!(parse_lint_matches(matches, &crate::config::Config::default()), expected_arguments)
+    };
+
+    expect_result("flf lint", ".", false);
+    expect_result("flf lint dir", "dir", false);
+    expect_result("flf lint --format text", ".", false);
+    expect_result("flf lint --format json dir", "dir", true);
+}
+
+
+fn build_watch_subcommand() -> clap::Command {
+
+    use clap::{Command, Arg, ArgAction};
+
+    let command = Command::new("watch")
+        .about("keep a flattened file up to date, rebuilding only the subtrees whose ignore files or directories changed (Linux only)");
+
+    // output file
+    let command = command
+        .arg(Arg::new("output")
+            .short('o')
+            .long("output")
+            .value_name("FILE")
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .help("file rewritten atomically after every change")
+            .action(ArgAction::Set)
+            .required(true)
+            .id("output"));
+
+    // comments and output order
+    let command = build_output_arguments(command);
+
+    // search path
+    let command = command
+        .arg(Arg::new("path")
+            .action(ArgAction::Set)
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .default_value(".")
+            .id("path"));
+
+    // command line patterns
+    let command = build_rule_arguments(command);
+
+    return command;
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct WatchArguments {
+
+    pub path: std::path::PathBuf,
+    pub ignore_file_name: String,
+    pub rules: RuleArguments,
+    pub output: std::path::PathBuf,
+    pub keep_comments: bool,
+    pub order: crate::job::OutputOrder
+}
+
+pub fn parse_watch_matches(matches: &clap::ArgMatches, config: &crate::config::Config) -> WatchArguments {
+
+    let path = matches.get_one::<std::path::PathBuf>("path").expect("invalid matches").to_owned();
+    let ignore_file_name = parse_ignore_file_name(matches, config);
+    let rules = parse_rule_arguments(matches, config);
+    let output = matches.get_one::<std::path::PathBuf>("output").expect("invalid matches").to_owned();
+    let keep_comments = parse_keep_comments(matches, config);
+    let order = parse_order(matches, config);
+
+    return WatchArguments{path, ignore_file_name, rules, output, keep_comments, order};
+}
+
+#[test]
+fn test_watch_matches_parser() {
+
+    use crate::job::OutputOrder;
+
+    let parser = build_cli_parser();
+
+    let expect_result = |args: &str, path: &str, output: &str, keep_comments: bool, order: OutputOrder| {
+
+        let expected_arguments = WatchArguments{
+            path: std::path::PathBuf::from(path),
+            ignore_file_name: ".gitignore".to_owned(),
+            rules: RuleArguments::default(),
+            output: std::path::PathBuf::from(output),
+            keep_comments,
+            order
+        };
+
+        let matches = parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments");
+        let (name, matches) = matches.subcommand().expect("missing subcommand");
+
+        assert_eq!(name, "watch");
+        assert_eq!(parse_watch_matches(matches, &crate::config::Config::default()), expected_arguments)
+    };
+
+    expect_result("flf watch -o out", ".", "out", false, OutputOrder::DepthFirst);
+    expect_result("flf watch --output out dir", "dir", "out", false, OutputOrder::DepthFirst);
+    expect_result("flf watch -o out --keep-comments --order breadth-first", ".", "out", true, OutputOrder::BreadthFirst);
+    expect_result("flf watch -o out --keep-comments --no-keep-comments", ".", "out", false, OutputOrder::DepthFirst);
+}
+
+
+fn build_pack_subcommand() -> clap::Command {
+
+    use clap::{Command, Arg, ArgAction};
+
+    let command = Command::new("pack")
+        .about("write the files that aren't ignored to an archive, keeping their modes and symlinks");
+
+    // output archive
+    let command = command
+        .arg(Arg::new("out")
+            .short('o')
+            .long("out")
+            .value_name("FILE")
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .help("archive to write, the format comes from the .tar, .tar.gz, .tgz or .zip extension")
+            .action(ArgAction::Set)
+            .required(true)
+            .id("out"));
+
+    // search path
+    let command = command
+        .arg(Arg::new("path")
+            .action(ArgAction::Set)
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .default_value(".")
+            .id("path"));
+
+    // command line patterns
+    let command = build_rule_arguments(command);
+
+    return command;
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PackArguments {
+
+    pub path: std::path::PathBuf,
+    pub ignore_file_name: String,
+    pub rules: RuleArguments,
+    pub output: std::path::PathBuf
+}
+
+pub fn parse_pack_matches(matches: &clap::ArgMatches, config: &crate::config::Config) -> PackArguments {
+
+    let path = matches.get_one::<std::path::PathBuf>("path").expect("invalid matches").to_owned();
+    let ignore_file_name = parse_ignore_file_name(matches, config);
+    let rules = parse_rule_arguments(matches, config);
+    let output = matches.get_one::<std::path::PathBuf>("out").expect("invalid matches").to_owned();
+
+    return PackArguments{path, ignore_file_name, rules, output};
+}
+
+#[test]
+fn test_pack_matches_parser() {
+
+    let parser = build_cli_parser();
+
+    let expect_result = |args: &str, path: &str, output: &str| {
+
+        let expected_arguments = PackArguments{path: std::path::PathBuf::from(path), ignore_file_name: ".gitignore".to_owned(), rules: RuleArguments::default(), output: std::path::PathBuf::from(output)};
+
+        let matches = parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments");
+        let (name, matches) = matches.subcommand().expect("missing subcommand");
+
+        assert_eq!(name, "pack");
+        assert_eq!(parse_pack_matches(matches, &crate::config::Config::default()), expected_arguments)
+    };
+
+    expect_result("flf pack --out dist.tar.gz", ".", "dist.tar.gz");
+    expect_result("flf pack -o dist.zip dir", "dir", "dist.zip");
+}
+
+
+fn build_copy_subcommand() -> clap::Command {
+
+    use clap::{Command, Arg, ArgAction};
+
+    let command = Command::new("copy")
+        .about("copy the files that aren't ignored to an empty or missing directory, keeping their modes and symlinks");
+
+    // destination
+    let command = command
+        .arg(Arg::new("dest")
+            .action(ArgAction::Set)
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .required(true)
+            .id("dest"));
+
+    // search path
+    let command = command
+        .arg(Arg::new("path")
+            .action(ArgAction::Set)
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .default_value(".")
+            .id("path"));
+
+    // command line patterns
+    let command = build_rule_arguments(command);
+
+    return command;
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CopyArguments {
+
+    pub path: std::path::PathBuf,
+    pub ignore_file_name: String,
+    pub rules: RuleArguments,
+    pub dest: std::path::PathBuf
+}
+
+pub fn parse_copy_matches(matches: &clap::ArgMatches, config: &crate::config::Config) -> CopyArguments {
+
+    let path = matches.get_one::<std::path::PathBuf>("path").expect("invalid matches").to_owned();
+    let ignore_file_name = parse_ignore_file_name(matches, config);
+    let rules = parse_rule_arguments(matches, config);
+    let dest = matches.get_one::<std::path::PathBuf>("dest").expect("invalid matches").to_owned();
+
+    return CopyArguments{path, ignore_file_name, rules, dest};
+}
+
+#[test]
+fn test_copy_matches_parser() {
+
+    let parser = build_cli_parser();
+
+    let expect_result = |args: &str, path: &str, dest: &str| {
+
+        let expected_arguments = CopyArguments{path: std::path::PathBuf::from(path), ignore_file_name: ".gitignore".to_owned(), rules: RuleArguments::default(), dest: std::path::PathBuf::from(dest)};
+
+        let matches = parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments");
+        let (name, matches) = matches.subcommand().expect("missing subcommand");
+
+        assert_eq!(name, "copy");
+        assert_eq!(parse_copy_matches(matches, &crate::config::Config::default()), expected_arguments)
+    };
+
+    expect_result("flf copy dest", ".", "dest");
+    expect_result("flf copy dest dir", "dir", "dest");
+}
+
+
+fn build_diff_subcommand() -> clap::Command {
+
+    use clap::{Command, Arg, ArgAction};
+
+    let command = Command::new("diff")
+        .about("list the paths whose ignored status differs between two trees, \"+\" for the newly ignored ones and \"-\" for the newly included ones");
+
+    // compared trees
+    let command = command
+        .arg(Arg::new("old root")
+            .action(ArgAction::Set)
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .required(true)
+            .id("old_root"))
+        .arg(Arg::new("new root")
+            .action(ArgAction::Set)
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .required(true)
+            .id("new_root"));
+
+    // command line patterns
+    let command = build_rule_arguments(command);
+
+    return command;
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DiffArguments {
+
+    pub old_root: std::path::PathBuf,
+    pub new_root: std::path::PathBuf,
+    pub ignore_file_name: String,
+    pub rules: RuleArguments
+}
+
+pub fn parse_diff_matches(matches: &clap::ArgMatches, config: &crate::config::Config) -> DiffArguments {
+
+    let old_root = matches.get_one::<std::path::PathBuf>("old_root").expect("invalid matches").to_owned();
+    let new_root = matches.get_one::<std::path::PathBuf>("new_root").expect("invalid matches").to_owned();
+    let ignore_file_name = parse_ignore_file_name(matches, config);
+    let rules = parse_rule_arguments(matches, config);
+
+    return DiffArguments{old_root, new_root, ignore_file_name, rules};
+}
+
+#[test]
+fn test_diff_matches_parser() {
+
+    let parser = build_cli_parser();
+
+    let expect_result = |args: &str, old_root: &str, new_root: &str, ignore_file_name: &str| {
+
+        let expected_arguments = DiffArguments{old_root: std::path::PathBuf::from(old_root), new_root: std::path::PathBuf::from(new_root), ignore_file_name: ignore_file_name.to_owned(), rules: RuleArguments::default()};
+
+        let matches = parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments");
+        let (name, matches) = matches.subcommand().expect("missing subcommand");
+
+        assert_eq!(name, "diff");
+        assert_eq!(parse_diff_matches(matches, &crate::config::Config::default()), expected_arguments)
+    };
+
+    expect_result("flf diff old new", "old", "new", ".gitignore");
+    expect_result("flf diff -n .ignore old new", "old", "new", ".ignore");
+}
+
+
+fn build_completions_subcommand() -> clap::Command {
+
+    use clap::{Command, Arg, ArgAction};
+
+    let command = Command::new("completions")
+        .about("write the completion script of a shell to stdout");
+
+    // target shell
+    let command = command
+        .arg(Arg::new("shell")
+            .action(ArgAction::Set)
+            .value_parser(["bash", "zsh", "fish"])
+            .required(true)
+            .id("shell"));
+
+    return command;
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CompletionsArguments {
+
+    pub shell: clap_complete::Shell
+}
+
+pub fn parse_completions_matches(matches: &clap::ArgMatches) -> CompletionsArguments {
+
+    use clap_complete::Shell;
+
+    let shell = match matches.get_one::<String>("shell").expect("invalid matches").as_str() {
+
+        "bash" => Shell::Bash,
+        "zsh" => Shell::Zsh,
+        "fish" => Shell::Fish,
+        _ => panic!("invalid shell")
+    };
+
+    return CompletionsArguments{shell};
+}
+
+#[test]
+fn test_completions_matches_parser() {
+
+    use clap_complete::Shell;
+
+    let parser = build_cli_parser();
+
+    let expect_result = |args: &str, shell: Shell| {
+
+        let matches = parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments");
+        let (name, matches) = matches.subcommand().expect("missing subcommand");
+
+        assert_eq!(name, "completions");
+        assert_eq!(parse_completions_matches(matches), CompletionsArguments{shell})
+    };
+
+    expect_result("flf completions bash", Shell::Bash);
+    expect_result("flf completions zsh", Shell::Zsh);
+    expect_result("flf completions fish", Shell::Fish);
+}
+
+
+fn build_man_subcommand() -> clap::Command {
+
+    use clap::{Command, Arg, ArgAction};
+
+    let command = Command::new("man")
+        .about("write the man page to stdout, or one page per command to a directory");
+
+    // output directory
+    let command = command
+        .arg(Arg::new("out dir")
+            .short('o')
+            .long("out-dir")
+            .value_name("DIR")
+            .value_parser(clap::value_parser!(std::path::PathBuf))
+            .help("directory receiving \"flf.1\" and a \"flf-<subcommand>.1\" page per subcommand")
+            .action(ArgAction::Set)
+            .id("out_dir"));
+
+    return command;
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ManArguments {
+
+    pub out_dir: Option<std::path::PathBuf>
+}
+
+pub fn parse_man_matches(matches: &clap::ArgMatches) -> ManArguments {
+
+    let out_dir = matches.get_one::<std::path::PathBuf>("out_dir").cloned();
+
+    return ManArguments{out_dir};
+}
+
+#[test]
+fn test_man_matches_parser() {
+
+    let parser = build_cli_parser();
+
+    let expect_result = |args: &str, out_dir: Option<&str>| {
+
+        let matches = parser.clone().try_get_matches_from(args.split_ascii_whitespace()).expect("invalid arguments");
+        let (name, matches) = matches.subcommand().expect("missing subcommand");
+
+        assert_eq!(name, "man");
+        assert_eq!(parse_man_matches(matches), ManArguments{out_dir: out_dir.map(std::path::PathBuf::from)})
+    };
+
+    expect_result("flf man", None);
+    expect_result("flf man --out-dir share/man/man1", Some("share/man/man1"));
+    expect_result("flf man -o man", Some("man"));
+}
@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+use crate::job::OutputOrder;
+use crate::output::NonUtf8Mode;
+
+
+
+pub const CONFIG_FILE_NAME: &str = "flf.toml";
+
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PathMode {
+
+    // patterns are prefixed with the search path as given
+    Relative,
+
+    // the search path is made absolute first
+    Absolute
+}
+
+
+// default options of a project, the command line takes precedence over every key
+#[derive(Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+
+    pub ignore_file_name: Option<String>,
+    pub keep_comments: Option<bool>,
+    pub non_utf8: Option<NonUtf8Mode>,
+    pub order: Option<OutputOrder>,
+    pub paths: Option<PathMode>,
+
+    // relative to the directory of the configuration file
+    pub cache: Option<PathBuf>,
+
+    // pruned from the traversal, but not written to the output
+    pub exclude_dirs: Vec<String>,
+
+    // applied to the whole tree and written before the patterns of its ignore files
+    pub global_patterns: Vec<String>,
+
+    // file the configuration was read from
+    #[serde(skip)]
+    pub path: Option<PathBuf>
+}
+
+impl Config {
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+
+        let content = std::fs::read_to_string(path)?;
+        let mut config = toml::from_str::<Config>(&content)?;
+
+        if let Some(cache) = config.cache.take() {
+
+            config.cache = Some(path.parent().expect("invalid configuration path").join(cache));
+        }
+
+        config.path = Some(path.to_owned());
+
+        return Ok(config);
+    }
+}
+
+
+// the closest "flf.toml" from the search path upward, otherwise the one of the user configuration directory
+pub fn find_config(start: &Path) -> anyhow::Result<Config> {
+
+    return find_config_in(start, user_config_dir().as_deref());
+}
+
+fn find_config_in(start: &Path, user_config_dir: Option<&Path>) -> anyhow::Result<Config> {
+
+    // archives are searched from their directory
+    let start = std::path::absolute(start)?;
+    let start_dir = if start.is_file() { start.parent().expect("invalid file path") } else { start.as_path() };
+
+    let project_config = start_dir.ancestors().map(|dir| dir.join(CONFIG_FILE_NAME)).find(|path| path.is_file());
+    let user_config = user_config_dir.map(|dir| dir.join(CONFIG_FILE_NAME)).filter(|path| path.is_file());
+
+    return match project_config.or(user_config) {
+
+        Some(path) => Config::load(&path).map_err(|err| anyhow::anyhow!("invalid configuration, path: {}, error: {}", path.display(), err)),
+        None => Ok(Config::default())
+    };
+}
+
+// "$XDG_CONFIG_HOME/flf", defaulting to "~/.config/flf" as in the XDG base directory specification
+fn user_config_dir() -> Option<PathBuf> {
+
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    return Some(config_home.join("flf"));
+}
+
+#[test]
+fn test_find_config() {
+
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("root")
+        .add_dir(Dir::new("home")
+            .add_file(File::new(CONFIG_FILE_NAME, b"ignore-file-name = \".ignore\"\n".to_vec())))
+        .add_dir(Dir::new("project")
+            .add_file(File::new(CONFIG_FILE_NAME, b"order = \"breadth-first\"\npaths = \"absolute\"\ncache = \"target/flf-cache\"\nexclude-dirs = [\"node_modules\"]\nglobal-patterns = [\"*.swp\"]\n".to_vec()))
+            .add_file(File::new_empty("dist.tar"))
+            .add_dir(Dir::new("a")
+                .add_dir(Dir::new("b"))))
+        .add_dir(Dir::new("other")
+            .add_file(File::new(CONFIG_FILE_NAME, b"unknown-key = true\n".to_vec())))
+        .add_dir(Dir::new("broken")
+            .add_file(File::new(CONFIG_FILE_NAME, b"order = \"sideways\"\n".to_vec())));
+
+    let fs = TmpFilesystem::new(&fs_template);
+    let root = fs.path().join("root");
+    let home = root.join("home");
+
+    let project_config = Config{
+        order: Some(OutputOrder::BreadthFirst),
+        paths: Some(PathMode::Absolute),
+        cache: Some(root.join("project/target/flf-cache")),
+        exclude_dirs: vec!["node_modules".to_owned()],
+        global_patterns: vec!["*.swp".to_owned()],
+        path: Some(root.join("project").join(CONFIG_FILE_NAME)),
+        ..Config::default()
+    };
+
+    // the closest file wins over the user one
+    assert_eq!(find_config_in(&root.join("project/a/b"), Some(&home)).unwrap(), project_config);
+    assert_eq!(find_config_in(&root.join("project/dist.tar"), Some(&home)).unwrap(), project_config);
+
+    let user_config = Config{ignore_file_name: Some(".ignore".to_owned()), path: Some(home.join(CONFIG_FILE_NAME)), ..Config::default()};
+    assert_eq!(find_config_in(&root, Some(&home)).unwrap(), user_config);
+    assert_eq!(find_config_in(&root, None).unwrap(), Config::default());
+
+    assert!(find_config_in(&root.join("other"), Some(&home)).is_err());
+    assert!(find_config_in(&root.join("broken"), Some(&home)).is_err());
+}
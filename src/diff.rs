@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::file_system::{EntryType, FileSystem};
+use crate::ignore_node::IgnoreNode;
+use crate::job::RootRules;
+
+
+
+// entry whose status differs between the two trees, the descendants of a changed directory aren't listed
+#[derive(Debug, PartialEq, Eq)]
+pub struct StatusChange {
+
+    // relative to the roots
+    pub path: PathBuf,
+    pub is_dir: bool,
+
+    // status under the new rules
+    pub ignored: bool
+}
+
+
+struct DiffJob {
+
+    relative_path: PathBuf,
+    old_dir: PathBuf,
+    new_dir: PathBuf,
+    old_context: Option<Arc<IgnoreNode>>,
+    new_context: Option<Arc<IgnoreNode>>
+}
+
+// walks the union of both trees, judging every entry with the ignore files of each one
+// the root rules are anchored at each root, so they're given once per tree
+pub fn diff_trees(old_fs: &dyn FileSystem, old_root: &Path, old_rules: Option<&RootRules>, new_fs: &dyn FileSystem, new_root: &Path, new_rules: Option<&RootRules>, ignore_file_name: &str) -> Vec<StatusChange> {
+
+    let (old_context, new_context) = (old_rules.and_then(RootRules::context), new_rules.and_then(RootRules::context));
+
+    let mut changes = Vec::new();
+    let mut jobs = vec![DiffJob{relative_path: PathBuf::new(), old_dir: old_root.to_owned(), new_dir: new_root.to_owned(), old_context, new_context}];
+
+    while let Some(job) = jobs.pop() {
+
+        let DiffJob{relative_path, old_dir, new_dir, old_context, new_context} = job;
+
+        let old_context = IgnoreNode::from_dir(old_fs, &old_dir, ignore_file_name, old_context);
+        let new_context = IgnoreNode::from_dir(new_fs, &new_dir, ignore_file_name, new_context);
+
+        // an entry that is a directory in either tree is judged as one
+        let mut entries = BTreeMap::<OsString, bool>::new();
+
+        for (fs, dir) in [(old_fs, &old_dir), (new_fs, &new_dir)] {
+
+            for (path, entry_type) in fs.read_dir(dir).unwrap_or_default() {
+
+                *entries.entry(path.file_name().expect("invalid directory entry").to_owned()).or_default() |= entry_type == EntryType::Dir;
+            }
+        }
+
+        let is_ignored = |context: &Option<Arc<IgnoreNode>>, path: &Path, is_dir: bool| context.as_ref().is_some_and(|matcher| matcher.matched(path, is_dir).is_ignore());
+
+        for (name, is_dir) in entries {
+
+            let (old_path, new_path) = (old_dir.join(&name), new_dir.join(&name));
+            let old_ignored = is_ignored(&old_context, &old_path, is_dir);
+            let new_ignored = is_ignored(&new_context, &new_path, is_dir);
+
+            if old_ignored != new_ignored {
+
+                changes.push(StatusChange{path: relative_path.join(&name), is_dir, ignored: new_ignored});
+            }
+            else if is_dir && new_ignored == false {
+
+                jobs.push(DiffJob{
+                    relative_path: relative_path.join(&name),
+                    old_dir: old_path,
+                    new_dir: new_path,
+                    old_context: old_context.clone(),
+                    new_context: new_context.clone()
+                });
+            }
+        }
+    }
+
+    changes.sort_by(|change1, change2| change1.path.cmp(&change2.path));
+    return changes;
+}
+
+#[test]
+fn test_diff_trees() {
+
+    use crate::file_system::MemoryFileSystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let old_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["*.log", "build/"]))
+        .add_file(File::new_empty("main.log"))
+        .add_file(File::new_empty("main.rs"))
+        .add_dir(Dir::new("build")
+            .add_file(File::new_empty("a.o")))
+        .add_dir(Dir::new("src")
+            .add_file(File::new_empty("debug.log"))
+            .add_file(File::new_empty("lib.rs"))
+            .add_file(File::new_empty("old.tmp")))
+        .add_dir(Dir::new("vendor")
+            .add_file(File::new_empty("dep.rs")));
+
+    let new_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["*.log", "vendor/"]))
+        .add_file(File::new_empty("main.log"))
+        .add_file(File::new_empty("main.rs"))
+        .add_dir(Dir::new("build")
+            .add_file(File::new_empty("a.o")))
+        .add_dir(Dir::new("src")
+            .add_file(File::new_gitignore(&["!debug.log", "*.tmp"]))
+            .add_file(File::new_empty("debug.log"))
+            .add_file(File::new_empty("lib.rs")))
+        .add_dir(Dir::new("vendor")
+            .add_file(File::new_empty("dep.rs")));
+
+    let old_fs = MemoryFileSystem::from_template(Path::new("/old"), &old_template);
+    let new_fs = MemoryFileSystem::from_template(Path::new("/new"), &new_template);
+
+    let change = |path: &str, is_dir: bool, ignored: bool| StatusChange{path: PathBuf::from(path), is_dir, ignored};
+
+    // "src/old.tmp" only exists in the old tree, it's still judged by the new rules
+    assert_eq!(diff_trees(&old_fs, Path::new("/old/dir"), None, &new_fs, Path::new("/new/dir"), None, ".gitignore"), [
+        change("build", true, false),
+        change("src/debug.log", false, false),
+        change("src/old.tmp", false, true),
+        change("vendor", true, true)
+    ]);
+
+    assert_eq!(diff_trees(&new_fs, Path::new("/new/dir"), None, &new_fs, Path::new("/new/dir"), None, ".gitignore"), []);
+
+    // each tree gets the same rules, anchored at its own root
+    let rules = |root: &str| {
+
+        let mut rules = RootRules::default();
+        rules.add_excluded_dirs(Path::new(root), Path::new("/flf.toml"), &["/src".to_owned()]);
+
+        return rules;
+    };
+
+    let (old_rules, new_rules) = (rules("/old/dir"), rules("/new/dir"));
+
+    assert_eq!(diff_trees(&old_fs, Path::new("/old/dir"), Some(&old_rules), &new_fs, Path::new("/new/dir"), Some(&new_rules), ".gitignore"), [
+        change("build", true, false),
+        change("vendor", true, true)
+    ]);
+}
+
+
+// changes grouped by parent directory, "+" marks the newly ignored entries and "-" the newly included ones
+pub fn format_changes(changes: &[StatusChange]) -> Vec<String> {
+
+    let mut groups = BTreeMap::<&Path, Vec<&StatusChange>>::new();
+
+    for change in changes {
+
+        groups.entry(change.path.parent().expect("invalid change path")).or_default().push(change);
+    }
+
+    let mut lines = Vec::new();
+
+    for (dir, group_changes) in groups {
+
+        let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+        lines.push(format!("{}/", dir.display()));
+
+        for change in group_changes {
+
+            let marker = if change.ignored { '+' } else { '-' };
+            let suffix = if change.is_dir { "/" } else { "" };
+            let name = Path::new(change.path.file_name().expect("invalid change path"));
+
+            lines.push(format!("  {} {}{}", marker, name.display(), suffix));
+        }
+    }
+
+    return lines;
+}
+
+#[test]
+fn test_format_changes() {
+
+    let change = |path: &str, is_dir: bool, ignored: bool| StatusChange{path: PathBuf::from(path), is_dir, ignored};
+
+    let changes = [
+        change("build", true, false),
+        change("src/debug.log", false, false),
+        change("src/old.tmp", false, true),
+        change("vendor", true, true)
+    ];
+
+    assert_eq!(format_changes(&changes), ["./", "  - build/", "  + vendor/", "src/", "  - debug.log", "  + old.tmp"]);
+    assert!(format_changes(&[]).is_empty());
+}
@@ -0,0 +1,238 @@
+use std::path::{Path, PathBuf, Component};
+use crate::file_system::{EntryType, FileSystem};
+use crate::ignore_node::IgnoreNode;
+use crate::job::RootRules;
+
+
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Rule {
+
+    pub source: Option<PathBuf>,
+    pub pattern: String
+}
+
+impl Rule {
+
+    fn from_glob(glob: &ignore::gitignore::Glob) -> Self {
+
+        return Self{source: glob.from().map(Path::to_owned), pattern: glob.original().to_owned()};
+    }
+}
+
+impl std::fmt::Display for Rule {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        match self.source {
+
+            Some(ref source) => write!(f, "\"{}\" ({})", self.pattern, source.display()),
+            None => write!(f, "\"{}\"", self.pattern)
+        }
+    }
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Decision {
+
+    Ignored(Rule),
+    Whitelisted(Rule),
+    NotMatched
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Explanation {
+
+    // deciding match of the path itself
+    pub decision: Decision,
+
+    // first ancestor directory excluded by the traversal, the path can't be reached through it
+    pub ignored_parent: Option<(PathBuf, Rule)>
+}
+
+impl Explanation {
+
+    pub fn is_ignored(&self) -> bool {
+
+        return self.ignored_parent.is_some() || matches!(self.decision, Decision::Ignored(_));
+    }
+}
+
+
+pub fn explain_path(fs: &dyn FileSystem, root: &Path, path: &Path, ignore_file_name: &str, root_rules: Option<&RootRules>) -> anyhow::Result<Explanation> {
+
+    let relative_path = strip_root(root, path).ok_or_else(|| anyhow::anyhow!("path outside of the search root \"{}\"", root.display()))?;
+    let mut components = relative_path.components().collect::<Vec<_>>();
+
+    let file_name = components.pop().ok_or_else(|| anyhow::anyhow!("the search root can't be explained"))?;
+
+    let mut dir = root.to_owned();
+    let mut ignore_context = IgnoreNode::from_dir(fs, &dir, ignore_file_name, root_rules.and_then(RootRules::context));
+    let mut ignored_parent = None;
+
+    for component in components {
+
+        let child_dir = dir.join(component);
+
+        if let (None, Some(context)) = (&ignored_parent, &ignore_context) {
+
+            if let ignore::Match::Ignore(glob) = context.matched(&child_dir, true) {
+
+                ignored_parent = Some((child_dir.clone(), Rule::from_glob(glob)));
+            }
+        }
+
+        dir = child_dir;
+        ignore_context = IgnoreNode::from_dir(fs, &dir, ignore_file_name, ignore_context);
+    }
+
+    let full_path = dir.join(file_name);
+
+    let decision = match ignore_context {
+
+        None => Decision::NotMatched,
+        Some(ref context) => match context.matched(&full_path, fs.entry_type(&full_path) == Some(EntryType::Dir)) {
+
+            ignore::Match::None => Decision::NotMatched,
+            ignore::Match::Ignore(glob) => Decision::Ignored(Rule::from_glob(glob)),
+            ignore::Match::Whitelist(glob) => Decision::Whitelisted(Rule::from_glob(glob))
+        }
+    };
+
+    return Ok(Explanation{decision, ignored_parent});
+}
+
+#[test]
+fn test_explain_path() {
+
+    use crate::file_system::MemoryFileSystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["*.log", "build/"]))
+        .add_dir(Dir::new("build")
+            .add_file(File::new_gitignore(&["!keep.log"]))
+            .add_file(File::new_empty("keep.log")))
+        .add_dir(Dir::new("src")
+            .add_file(File::new_gitignore(&["!important.log"]))
+            .add_file(File::new_empty("important.log"))
+            .add_file(File::new_empty("debug.log"))
+            .add_file(File::new_empty("main.rs")));
+
+    let fs = MemoryFileSystem::from_template(Path::new("/"), &fs_template);
+    let root = PathBuf::from("/dir");
+
+    let rule = |source: &str, pattern: &str| Rule{source: Some(root.join(source)), pattern: pattern.to_owned()};
+    let explain = |path: &str| explain_path(&fs, &root, &root.join(path), ".gitignore", None).expect("unexpected explain error");
+
+    assert_eq!(explain("src/main.rs"), Explanation{decision: Decision::NotMatched, ignored_parent: None});
+    assert_eq!(explain("src/debug.log"), Explanation{decision: Decision::Ignored(rule(".gitignore", "*.log")), ignored_parent: None});
+    assert_eq!(explain("src/important.log"), Explanation{decision: Decision::Whitelisted(rule("src/.gitignore", "!important.log")), ignored_parent: None});
+    assert_eq!(explain("build"), Explanation{decision: Decision::Ignored(rule(".gitignore", "build/")), ignored_parent: None});
+    assert_eq!(explain("missing.log"), Explanation{decision: Decision::Ignored(rule(".gitignore", "*.log")), ignored_parent: None});
+
+    // whitelisted inside an ignored directory
+    let explanation = explain("build/keep.log");
+    assert_eq!(explanation, Explanation{decision: Decision::Whitelisted(rule("build/.gitignore", "!keep.log")), ignored_parent: Some((root.join("build"), rule(".gitignore", "build/")))});
+    assert!(explanation.is_ignored());
+
+    // the root rules are overridden by the ignore files of the tree
+    let mut rules = RootRules::default();
+    rules.add_patterns(&root, Path::new("/flf.toml"), &["*.rs".to_owned(), "!*.log".to_owned()], false);
+
+    let explain_with_rules = |path: &str| explain_path(&fs, &root, &root.join(path), ".gitignore", Some(&rules)).expect("unexpected explain error");
+    let config_rule = |pattern: &str| Rule{source: Some(PathBuf::from("/flf.toml")), pattern: pattern.to_owned()};
+
+    assert_eq!(explain_with_rules("src/main.rs"), Explanation{decision: Decision::Ignored(config_rule("*.rs")), ignored_parent: None});
+    assert_eq!(explain_with_rules("src/debug.log"), Explanation{decision: Decision::Ignored(rule(".gitignore", "*.log")), ignored_parent: None});
+
+    assert!(explain_path(&fs, &root, &root, ".gitignore", None).is_err());
+    assert!(explain_path(&fs, &root, Path::new("/other"), ".gitignore", None).is_err());
+}
+
+
+pub fn format_explanation(path: &Path, explanation: &Explanation) -> String {
+
+    let decision = match explanation.decision {
+
+        Decision::Ignored(ref rule) => format!("ignored by {}", rule),
+        Decision::Whitelisted(ref rule) => format!("included by {}", rule),
+        Decision::NotMatched => "not matched".to_owned()
+    };
+
+    return match explanation.ignored_parent {
+
+        Some((ref parent, ref rule)) => format!("{}: unreachable, parent directory \"{}\" ignored by {}, path {}", path.display(), parent.display(), rule, decision),
+        None => format!("{}: {}", path.display(), decision)
+    };
+}
+
+#[test]
+fn test_format_explanation() {
+
+    let path = Path::new("dir/foo.log");
+    let rule = |pattern: &str| Rule{source: Some(PathBuf::from("dir/.gitignore")), pattern: pattern.to_owned()};
+
+    assert_eq!(format_explanation(path, &Explanation{decision: Decision::NotMatched, ignored_parent: None}), "dir/foo.log: not matched");
+    assert_eq!(format_explanation(path, &Explanation{decision: Decision::Ignored(rule("*.log")), ignored_parent: None}), "dir/foo.log: ignored by \"*.log\" (dir/.gitignore)");
+    assert_eq!(format_explanation(path, &Explanation{decision: Decision::Whitelisted(rule("!foo.log")), ignored_parent: None}), "dir/foo.log: included by \"!foo.log\" (dir/.gitignore)");
+    assert_eq!(
+        format_explanation(path, &Explanation{decision: Decision::NotMatched, ignored_parent: Some((PathBuf::from("dir"), rule("dir/")))}),
+        "dir/foo.log: unreachable, parent directory \"dir\" ignored by \"dir/\" (dir/.gitignore), path not matched"
+    );
+}
+
+
+// returns the path relative to the root, both are made absolute first and their ".." components are resolved lexically
+fn strip_root(root: &Path, path: &Path) -> Option<PathBuf> {
+
+    // "std::path::absolute" keeps the ".." components on unix
+    let normalize = |path: &Path| -> Option<PathBuf> {
+
+        let mut normalized = PathBuf::new();
+
+        for component in std::path::absolute(path).ok()?.components() {
+
+            match component {
+
+                Component::CurDir => {},
+                Component::ParentDir => { normalized.pop(); },
+                _ => normalized.push(component)
+            }
+        }
+
+        return Some(normalized);
+    };
+
+    return normalize(path)?.strip_prefix(normalize(root)?).ok().map(Path::to_owned);
+}
+
+#[test]
+fn test_strip_root() {
+
+    #[track_caller]
+    fn assert_strip(root: &str, path: &str, expected: Option<&str>) {
+
+        assert_eq!(strip_root(Path::new(root), Path::new(path)), expected.map(PathBuf::from));
+    }
+
+    assert_strip(".", "foo/bar", Some("foo/bar"));
+    assert_strip(".", "./foo/bar", Some("foo/bar"));
+    assert_strip("./foo", "foo/bar", Some("bar"));
+    assert_strip("foo", "./foo/bar", Some("bar"));
+    assert_strip("foo", "foo", Some(""));
+    assert_strip("foo", "bar/foo", None);
+    assert_strip("foo", "foo/../bar/baz", None);
+    assert_strip(".", "a/../b", Some("b"));
+    assert_strip("foo", "foo/a/../b", Some("b"));
+    assert_strip("foo/../bar", "bar/baz", Some("baz"));
+
+    // relative and absolute paths are compared from the working directory
+    let cwd = std::env::current_dir().unwrap();
+    assert_strip(".", cwd.join("foo/bar").to_str().unwrap(), Some("foo/bar"));
+    assert_strip(cwd.join("foo").to_str().unwrap(), "foo/bar", Some("bar"));
+    assert_strip("/foo", "/foo/bar", Some("bar"));
+    assert_strip("/foo", "/bar", None);
+}
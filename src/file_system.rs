@@ -0,0 +1,283 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EntryType {
+
+    File,
+    Dir,
+    Symlink,
+
+    // sockets, devices, git submodules...
+    Other
+}
+
+
+// source of the directories and ignore files, so the traversal doesn't depend on the disk
+pub trait FileSystem {
+
+    // entries sorted by path, symlinks aren't followed
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<(PathBuf, EntryType)>>;
+
+    fn read_file(&self, path: &Path) -> std::io::Result<Vec<u8>>;
+
+    // like "Path::is_file", symlinks are followed when the file system supports them
+    fn entry_type(&self, path: &Path) -> Option<EntryType>;
+
+    // None when the source doesn't track modifications, its entries are then never reused by the cache
+    fn stamp(&self, _path: &Path) -> Option<EntryStamp> {
+
+        return None;
+    }
+
+    // size of the entry itself, symlinks aren't followed
+    fn file_size(&self, _path: &Path) -> Option<u64> {
+
+        return None;
+    }
+}
+
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct EntryStamp {
+
+    pub mtime: std::time::SystemTime,
+    pub size: u64
+}
+
+
+pub struct OsFileSystem;
+
+impl OsFileSystem {
+
+    fn entry_type_of(file_type: std::fs::FileType) -> EntryType {
+
+        return match file_type {
+
+            file_type if file_type.is_file() => EntryType::File,
+            file_type if file_type.is_dir() => EntryType::Dir,
+            file_type if file_type.is_symlink() => EntryType::Symlink,
+            _ => EntryType::Other
+        };
+    }
+}
+
+impl FileSystem for OsFileSystem {
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<(PathBuf, EntryType)>> {
+
+        let mut entries = Vec::new();
+
+        for entry in std::fs::read_dir(path)? {
+
+            let entry = entry?;
+            entries.push((entry.path(), Self::entry_type_of(entry.file_type()?)));
+        }
+
+        // "read_dir" order depends on the filesystem
+        entries.sort_by(|(path1, _), (path2, _)| path1.cmp(path2));
+
+        return Ok(entries);
+    }
+
+    fn read_file(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+
+        return std::fs::read(path);
+    }
+
+    fn entry_type(&self, path: &Path) -> Option<EntryType> {
+
+        return std::fs::metadata(path).ok().map(|metadata| Self::entry_type_of(metadata.file_type()));
+    }
+
+    fn stamp(&self, path: &Path) -> Option<EntryStamp> {
+
+        let metadata = std::fs::metadata(path).ok()?;
+
+        return Some(EntryStamp{mtime: metadata.modified().ok()?, size: metadata.len()});
+    }
+
+    fn file_size(&self, path: &Path) -> Option<u64> {
+
+        return std::fs::symlink_metadata(path).ok().map(|metadata| metadata.len());
+    }
+}
+
+#[test]
+fn test_os_file_system() {
+
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new("b", b"content".to_vec()))
+        .add_dir(Dir::new("c"))
+        .add_dir(Dir::new("a"));
+
+    let fs = TmpFilesystem::new(&fs_template);
+    let root = fs.path().join("dir");
+
+    assert_eq!(OsFileSystem.read_dir(&root).unwrap(), [
+        (root.join("a"), EntryType::Dir),
+        (root.join("b"), EntryType::File),
+        (root.join("c"), EntryType::Dir)
+    ]);
+
+    assert_eq!(OsFileSystem.read_file(&root.join("b")).unwrap(), b"content");
+    assert_eq!(OsFileSystem.entry_type(&root.join("a")), Some(EntryType::Dir));
+    assert_eq!(OsFileSystem.entry_type(&root.join("b")), Some(EntryType::File));
+    assert_eq!(OsFileSystem.entry_type(&root.join("missing")), None);
+    assert!(OsFileSystem.read_dir(&root.join("missing")).is_err());
+
+    assert_eq!(OsFileSystem.stamp(&root.join("b")).map(|stamp| stamp.size), Some(7));
+    assert_eq!(OsFileSystem.file_size(&root.join("b")), Some(7));
+    assert!(OsFileSystem.stamp(&root.join("missing")).is_none());
+}
+
+
+enum MemoryEntry {
+
+    Dir,
+    File(Vec<u8>),
+    Symlink
+}
+
+// tree held in memory, for archives and tests
+#[derive(Default)]
+pub struct MemoryFileSystem {
+
+    // descendants of a directory sort right after it
+    entries: BTreeMap<PathBuf, MemoryEntry>
+}
+
+impl MemoryFileSystem {
+
+    pub fn new() -> Self {
+
+        return Self::default();
+    }
+
+    // missing parent directories are created as well
+    pub fn add_dir(&mut self, path: &Path) {
+
+        for ancestor in path.ancestors().filter(|ancestor| ancestor.as_os_str().is_empty() == false) {
+
+            self.entries.entry(ancestor.to_owned()).or_insert(MemoryEntry::Dir);
+        }
+    }
+
+    pub fn add_file(&mut self, path: &Path, content: Vec<u8>) {
+
+        if let Some(parent) = path.parent() {
+
+            self.add_dir(parent);
+        }
+
+        self.entries.insert(path.to_owned(), MemoryEntry::File(content));
+    }
+
+    pub fn add_symlink(&mut self, path: &Path) {
+
+        if let Some(parent) = path.parent() {
+
+            self.add_dir(parent);
+        }
+
+        self.entries.insert(path.to_owned(), MemoryEntry::Symlink);
+    }
+
+    fn entry_type_of(entry: &MemoryEntry) -> EntryType {
+
+        return match entry {
+
+            MemoryEntry::Dir => EntryType::Dir,
+            MemoryEntry::File(_) => EntryType::File,
+            MemoryEntry::Symlink => EntryType::Symlink
+        };
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<(PathBuf, EntryType)>> {
+
+        if self.entry_type(path) != Some(EntryType::Dir) {
+
+            return Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("missing directory: {}", path.display())));
+        }
+
+        return Ok(self.entries.range(path.to_owned()..)
+            .skip(1)
+            .take_while(|(entry_path, _)| entry_path.starts_with(path))
+            .filter(|(entry_path, _)| entry_path.parent() == Some(path))
+            .map(|(entry_path, entry)| (entry_path.clone(), Self::entry_type_of(entry)))
+            .collect());
+    }
+
+    fn read_file(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+
+        return match self.entries.get(path) {
+
+            Some(MemoryEntry::File(content)) => Ok(content.clone()),
+            _ => Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("missing file: {}", path.display())))
+        };
+    }
+
+    // symlink targets aren't kept
+    fn entry_type(&self, path: &Path) -> Option<EntryType> {
+
+        return self.entries.get(path).map(Self::entry_type_of);
+    }
+
+    fn file_size(&self, path: &Path) -> Option<u64> {
+
+        return match self.entries.get(path) {
+
+            Some(MemoryEntry::File(content)) => Some(content.len() as u64),
+            _ => None
+        };
+    }
+}
+
+#[test]
+fn test_memory_file_system() {
+
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new("b", b"content".to_vec()))
+        .add_dir(Dir::new("c")
+            .add_file(File::new_empty("d")))
+        .add_dir(Dir::new("a"));
+
+    let mut fs = MemoryFileSystem::from_template(Path::new("/mnt"), &fs_template);
+    fs.add_symlink(Path::new("/mnt/dir/a/link"));
+    fs.add_file(Path::new("/mnt/dir-other/e"), Vec::new());
+
+    let root = Path::new("/mnt/dir");
+
+    assert_eq!(fs.read_dir(root).unwrap(), [
+        (root.join("a"), EntryType::Dir),
+        (root.join("b"), EntryType::File),
+        (root.join("c"), EntryType::Dir)
+    ]);
+    assert_eq!(fs.read_dir(&root.join("a")).unwrap(), [(root.join("a/link"), EntryType::Symlink)]);
+    assert_eq!(fs.read_dir(Path::new("/mnt")).unwrap(), [(root.to_owned(), EntryType::Dir), (PathBuf::from("/mnt/dir-other"), EntryType::Dir)]);
+    assert!(fs.read_dir(&root.join("b")).is_err());
+    assert!(fs.read_dir(&root.join("missing")).is_err());
+
+    assert_eq!(fs.read_file(&root.join("b")).unwrap(), b"content");
+    assert_eq!(fs.read_file(&root.join("c/d")).unwrap(), b"");
+    assert!(fs.read_file(&root.join("c")).is_err());
+
+    assert_eq!(fs.entry_type(Path::new("/")), Some(EntryType::Dir));
+    assert_eq!(fs.entry_type(&root.join("c/d")), Some(EntryType::File));
+    assert_eq!(fs.entry_type(&root.join("missing")), None);
+    assert_eq!(fs.stamp(&root.join("b")), None);
+    assert_eq!(fs.file_size(&root.join("b")), Some(7));
+    assert_eq!(fs.file_size(&root.join("c")), None);
+}
@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::file_system::{EntryType, FileSystem, OsFileSystem};
+use crate::ignore_node::IgnoreNode;
+use crate::job::{self, OutputOrder};
+use crate::stats::{FlattenStats, StatsFileSystem};
+
+
+
+// one path per line, "-" reads from stdin
+pub fn read_file_list(source: &Path) -> std::io::Result<Vec<PathBuf>> {
+
+    let content = match source.as_os_str() == "-" {
+
+        true => {
+
+            use std::io::Read;
+
+            let mut content = Vec::new();
+            std::io::stdin().lock().read_to_end(&mut content)?;
+            content
+        },
+        false => std::fs::read(source)?
+    };
+
+    return parse_file_list(&content);
+}
+
+fn parse_file_list(content: &[u8]) -> std::io::Result<Vec<PathBuf>> {
+
+    return content.split(|byte| *byte == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| line.is_empty() == false)
+        .map(|line| crate::absolute_ignore::bytes_to_os_string(line.to_owned()).map(PathBuf::from))
+        .collect();
+}
+
+#[test]
+fn test_parse_file_list() {
+
+    assert_eq!(parse_file_list(b"").unwrap(), Vec::<PathBuf>::new());
+    assert_eq!(parse_file_list(b".gitignore\na/.gitignore").unwrap(), [PathBuf::from(".gitignore"), PathBuf::from("a/.gitignore")]);
+    assert_eq!(parse_file_list(b"a/.gitignore\r\n\n\nb c/.gitignore\n").unwrap(), [PathBuf::from("a/.gitignore"), PathBuf::from("b c/.gitignore")]);
+}
+
+
+// flattens the given ignore files without walking the tree, relative paths are resolved against the root
+pub fn flatten_files(root: &Path, files: &[PathBuf], keep_comments: bool, order: OutputOrder, root_rules: Option<&job::RootRules>, mut stats: Option<&mut FlattenStats>) -> Vec<OsString> {
+
+    let started = std::time::Instant::now();
+
+    // the IO is only timed when the stats are requested
+    let stats_fs = stats.as_ref().map(|_| StatsFileSystem::new(&OsFileSystem));
+    let fs: &dyn FileSystem = match stats_fs {
+
+        Some(ref stats_fs) => stats_fs,
+        None => &OsFileSystem
+    };
+
+    // parents sort before their children
+    let mut files_by_dir = BTreeMap::<PathBuf, Vec<OsString>>::new();
+
+    // a root like "." can't be compared with absolute entries as is, the patterns are still written relative to the given root
+    let canonical_root = root.canonicalize().unwrap_or_else(|err| {
+
+        tracing::error!(path = %root.display(), error = %err, "search path reading error");
+        std::process::exit(1);
+    });
+
+    for file in files {
+
+        let path = root.join(file);
+
+        let (Some(dir), Some(file_name)) = (path.parent(), path.file_name()) else {
+
+            tracing::warn!(path = %file.display(), "invalid ignore file path, skipping it");
+            continue;
+        };
+
+        let canonical_dir = dir.canonicalize().unwrap_or_else(|err| {
+
+            tracing::error!(path = %path.display(), error = %err, "missing ignore file");
+            std::process::exit(1);
+        });
+
+        let Ok(relative_dir) = canonical_dir.strip_prefix(&canonical_root) else {
+
+            tracing::warn!(path = %file.display(), "ignore file outside of the search path, skipping it");
+            continue;
+        };
+
+        let dir = match relative_dir.as_os_str().is_empty() {
+
+            true => root.to_owned(),
+            false => root.join(relative_dir)
+        };
+
+        files_by_dir.entry(dir).or_default().push(file_name.to_owned());
+    }
+
+    let mut contexts = BTreeMap::<PathBuf, Option<Arc<IgnoreNode>>>::new();
+    let mut sections = Vec::new();
+
+    if let Some(rules) = root_rules {
+
+        contexts.insert(root.to_owned(), rules.context());
+        sections.extend(rules.sections().iter().map(|section| (root.to_owned(), section.clone())));
+    }
+
+    // the context of a directory is the one of its closest ancestor with an ignore file
+    let context_of = |contexts: &BTreeMap<PathBuf, Option<Arc<IgnoreNode>>>, dir: &Path| dir.ancestors()
+        .take_while(|ancestor| ancestor.starts_with(root))
+        .find_map(|ancestor| contexts.get(ancestor))
+        .cloned()
+        .flatten();
+
+    for (dir, file_names) in files_by_dir {
+
+        // same pruning as the directory walk, every directory between the root and this one must be reachable
+        let excluded = dir.ancestors()
+            .take_while(|ancestor| *ancestor != root && ancestor.starts_with(root))
+            .any(|ancestor| job::is_pruned(&context_of(&contexts, ancestor.parent().expect("invalid path")), ancestor));
+
+        if let Some(ref mut stats) = stats {
+
+            match excluded {
+
+                true => stats.pruned_dirs += 1,
+                false => stats.visited_dirs += 1
+            }
+        }
+
+        if excluded {
+
+            continue;
+        }
+
+        let mut ignore_context = context_of(&contexts, &dir);
+
+        for file_name in file_names {
+
+            if fs.entry_type(&dir.join(&file_name)) != Some(EntryType::File) {
+
+                tracing::error!(path = %dir.join(&file_name).display(), "missing ignore file");
+                std::process::exit(1);
+            }
+
+            let (new_context, patterns) = job::load_local_ignore_file(fs, root, &dir, ignore_context, &file_name, keep_comments);
+            let patterns = patterns.expect("missing ignore file patterns");
+
+            if let Some(ref mut stats) = stats {
+
+                stats.add_ignore_file(&patterns, new_context.as_ref().map_or(0, |context| context.depth()));
+            }
+
+            ignore_context = new_context;
+            sections.push((dir.clone(), patterns));
+        }
+
+        contexts.insert(dir, ignore_context);
+    }
+
+    if let (Some(stats), Some(stats_fs)) = (stats, stats_fs) {
+
+        stats.io_time = stats_fs.io_time.get();
+        stats.matching_time = started.elapsed().saturating_sub(stats.io_time);
+    }
+
+    job::order_sections(root, &mut sections, order);
+
+    return job::join_sections(sections, keep_comments);
+}
+
+#[test]
+fn test_flatten_files() {
+
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["build/", "!build/keep/"]))
+        .add_dir(Dir::new("a")
+            .add_file(File::new_gitignore(&["*.log", "tmp/"]))
+            .add_dir(Dir::new("b")
+                .add_file(File::new_gitignore(&["foo"])))
+            .add_dir(Dir::new("tmp")
+                .add_file(File::new_gitignore(&["unreachable"]))))
+        .add_dir(Dir::new("build")
+            .add_file(File::new_gitignore(&["hidden"]))
+            .add_dir(Dir::new("keep")
+                .add_file(File::new_gitignore(&["also_hidden"]))))
+        .add_dir(Dir::new("c")
+            .add_dir(Dir::new("d")
+                .add_file(File::new_gitignore(&["bar"]))));
+
+    let fs = TmpFilesystem::new(&fs_template);
+    let root = fs.path().join("dir");
+
+    let all_files = [".gitignore", "a/.gitignore", "a/b/.gitignore", "a/tmp/.gitignore", "build/.gitignore", "build/keep/.gitignore", "c/d/.gitignore"]
+        .map(PathBuf::from);
+
+    // same result as the directory walk
+    for order in [OutputOrder::Path, OutputOrder::DepthFirst, OutputOrder::BreadthFirst] {
+
+        assert_eq!(flatten_files(&root, &all_files, false, order, None, None), job::flatten(&OsFileSystem, &root, ".gitignore", job::FlattenOptions{order, ..Default::default()}));
+        assert_eq!(flatten_files(&root, &all_files, true, order, None, None), job::flatten(&OsFileSystem, &root, ".gitignore", job::FlattenOptions{keep_comments: true, order, ..Default::default()}));
+    }
+
+    // subset in any order, the directories of unlisted files aren't pruned
+    let files = [PathBuf::from("c/d/.gitignore"), PathBuf::from("a/tmp/.gitignore"), root.join(".gitignore")];
+    let patterns = flatten_files(&root, &files, false, OutputOrder::DepthFirst, None, None).into_iter()
+        .map(|pattern| pattern.into_string().unwrap().replacen(root.to_str().unwrap(), "", 1))
+        .collect::<Vec<_>>();
+
+    assert_eq!(patterns, ["/**/build/", "!/build/keep/", "/a/tmp/**/unreachable", "/c/d/**/bar"]);
+
+    // only the directories of the listed files are visited or pruned
+    let mut stats = FlattenStats::default();
+    flatten_files(&root, &all_files, true, OutputOrder::DepthFirst, None, Some(&mut stats));
+
+    assert_eq!((stats.visited_dirs, stats.pruned_dirs, stats.ignore_files, stats.patterns), (4, 3, 4, 6));
+    assert_eq!((stats.max_chain_depth, stats.peak_job_stack), (3, 0));
+
+    // absolute entries under a root that isn't canonical, the patterns stay relative to the given root
+    let indirect_root = root.join("a/..");
+    let files = [root.join("c/d/.gitignore"), fs.path().join(".gitignore")];
+
+    assert_eq!(flatten_files(&indirect_root, &files, false, OutputOrder::DepthFirst, None, None), [indirect_root.join("c/d/**/bar").into_os_string()]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_flatten_non_utf8_files() {
+
+    use std::os::unix::ffi::OsStringExt;
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let file_name = OsString::from_vec(b"ignore_\xff".to_vec());
+
+    let fs_template = Dir::new("dir")
+        .add_dir(Dir::new("a")
+            .add_file(File::new(file_name.clone(), b"foo\n".to_vec())));
+
+    let fs = TmpFilesystem::new(&fs_template);
+    let root = fs.path().join("dir");
+
+    assert_eq!(flatten_files(&root, &[Path::new("a").join(&file_name)], false, OutputOrder::DepthFirst, None, None), [root.join("a/**/foo").into_os_string()]);
+}
@@ -0,0 +1,111 @@
+use std::cell::RefCell;
+use std::io::Write;
+use std::path::PathBuf;
+use super::template;
+use super::template::Visitor;
+
+
+
+// builds a template as an archive, the entries are relative to the archive root
+pub struct ArchiveBuilder {
+
+    // directories have no content
+    entries: RefCell<Vec<(PathBuf, Option<Vec<u8>>)>>
+}
+
+impl template::Visitor for ArchiveBuilder {
+
+    type DirParameter<'a> = &'a std::path::Path;
+    type DirReturnType = ();
+
+    fn visit_dir<'a>(&self, dir: &template::Dir, path: Self::DirParameter<'a>) -> Self::DirReturnType {
+
+        let path = path.join(&dir.name);
+        self.entries.borrow_mut().push((path.clone(), None));
+
+        dir.child_files.iter().for_each(|file| file.visit(self, &path));
+        dir.child_dirs.iter().for_each(|dir| dir.visit(self, &path));
+    }
+
+
+    type FileParameter<'a> = &'a std::path::Path;
+    type FileReturnType = ();
+
+    fn visit_file<'a>(&self, file: &template::File, path: Self::FileParameter<'a>) -> Self::FileReturnType {
+
+        let content = file.content.clone().unwrap_or_default();
+        self.entries.borrow_mut().push((path.join(&file.name), Some(content)));
+    }
+}
+
+impl ArchiveBuilder {
+
+    fn entries(dir: &template::Dir) -> Vec<(PathBuf, Option<Vec<u8>>)> {
+
+        let builder = Self{entries: RefCell::new(Vec::new())};
+        builder.visit_dir(dir, std::path::Path::new(""));
+
+        return builder.entries.into_inner();
+    }
+
+    pub fn build_tar(dir: &template::Dir) -> Vec<u8> {
+
+        let mut builder = tar::Builder::new(Vec::new());
+
+        for (path, content) in Self::entries(dir) {
+
+            let mut header = tar::Header::new_gnu();
+
+            match content {
+
+                Some(content) => {
+
+                    header.set_entry_type(tar::EntryType::Regular);
+                    header.set_mode(0o644);
+                    header.set_size(content.len() as u64);
+                    builder.append_data(&mut header, path, content.as_slice()).expect("tar entry writing error");
+                },
+                None => {
+
+                    header.set_entry_type(tar::EntryType::Directory);
+                    header.set_mode(0o755);
+                    header.set_size(0);
+                    builder.append_data(&mut header, path, std::io::empty()).expect("tar entry writing error");
+                }
+            }
+        }
+
+        return builder.into_inner().expect("tar writing error");
+    }
+
+    pub fn build_tar_gz(dir: &template::Dir) -> Vec<u8> {
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&Self::build_tar(dir)).expect("gzip writing error");
+
+        return encoder.finish().expect("gzip writing error");
+    }
+
+    pub fn build_zip(dir: &template::Dir) -> Vec<u8> {
+
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+
+        for (path, content) in Self::entries(dir) {
+
+            let name = path.to_str().expect("non-UTF-8 zip entry");
+
+            match content {
+
+                Some(content) => {
+
+                    writer.start_file(name, options).expect("zip entry writing error");
+                    writer.write_all(&content).expect("zip entry writing error");
+                },
+                None => writer.add_directory(name, options).expect("zip entry writing error")
+            }
+        }
+
+        return writer.finish().expect("zip writing error").into_inner();
+    }
+}
@@ -120,3 +120,25 @@ fn test_file_content() {
 
     assert!(Checker::check_dir(filesystem.path(), &dir_template));
 }
+
+#[cfg(unix)]
+#[test]
+fn test_non_utf8_names() {
+
+    use std::os::unix::ffi::OsStringExt;
+    use template::{Dir, File};
+    use super::tmp_filesystem::TmpFilesystem;
+    use super::checker::Checker;
+
+    let dir_name = std::ffi::OsString::from_vec(b"dir_\xff".to_vec());
+    let file_name = std::ffi::OsString::from_vec(b"file_\xfe".to_vec());
+
+    let dir_template = Dir::new("dir")
+        .add_dir(Dir::new(dir_name.clone())
+            .add_file(File::new_empty(file_name.clone())));
+
+    let filesystem = TmpFilesystem::new(&dir_template);
+
+    assert!(filesystem.path().join("dir").join(&dir_name).join(&file_name).is_file());
+    assert!(Checker::check_dir(filesystem.path(), &dir_template));
+}
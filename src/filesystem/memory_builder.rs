@@ -0,0 +1,49 @@
+use std::cell::RefCell;
+use super::template;
+use super::template::Visitor;
+use crate::file_system::MemoryFileSystem;
+
+
+
+// builds a template in memory, the counterpart of "Builder"
+pub struct MemoryBuilder {
+
+    fs: RefCell<MemoryFileSystem>
+}
+
+impl template::Visitor for MemoryBuilder {
+
+    type DirParameter<'a> = &'a std::path::Path;
+    type DirReturnType = ();
+
+    fn visit_dir<'a>(&self, dir: &template::Dir, path: Self::DirParameter<'a>) -> Self::DirReturnType {
+
+        let path = path.join(&dir.name);
+        self.fs.borrow_mut().add_dir(&path);
+
+        dir.child_files.iter().for_each(|file| file.visit(self, &path));
+        dir.child_dirs.iter().for_each(|dir| dir.visit(self, &path));
+    }
+
+
+    type FileParameter<'a> = &'a std::path::Path;
+    type FileReturnType = ();
+
+    fn visit_file<'a>(&self, file: &template::File, path: Self::FileParameter<'a>) -> Self::FileReturnType {
+
+        let content = file.content.clone().unwrap_or_default();
+        self.fs.borrow_mut().add_file(&path.join(&file.name), content);
+    }
+}
+
+impl MemoryFileSystem {
+
+    // same layout as "TmpFilesystem", the template directory is placed under the given path
+    pub fn from_template(path: &std::path::Path, dir: &template::Dir) -> Self {
+
+        let builder = MemoryBuilder{fs: RefCell::new(MemoryFileSystem::new())};
+        builder.visit_dir(dir, path);
+
+        return builder.fs.into_inner();
+    }
+}
@@ -4,3 +4,5 @@ pub mod template;
 pub mod builder;
 pub mod checker;
 pub mod tmp_filesystem;
+pub mod memory_builder;
+pub mod archive_builder;
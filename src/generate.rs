@@ -0,0 +1,222 @@
+use std::io::Write;
+use std::path::Path;
+use crate::cli;
+
+
+
+// name of the installed binary, the parser is named after the program
+pub const BIN_NAME: &str = "flf";
+
+
+fn build_named_parser() -> clap::Command {
+
+    return cli::build_cli_parser().name(BIN_NAME).bin_name(BIN_NAME);
+}
+
+pub fn write_completions(shell: clap_complete::Shell, writer: &mut dyn Write) {
+
+    clap_complete::generate(shell, &mut build_named_parser(), BIN_NAME, writer);
+}
+
+pub fn write_man_page(writer: &mut dyn Write) -> std::io::Result<()> {
+
+    return clap_mangen::Man::new(build_named_parser()).render(writer);
+}
+
+// "flf.1" and a "flf-<subcommand>.1" page per subcommand
+pub fn write_man_pages(out_dir: &Path) -> std::io::Result<()> {
+
+    std::fs::create_dir_all(out_dir)?;
+
+    return clap_mangen::generate_to(build_named_parser(), out_dir);
+}
+
+#[cfg(test)]
+fn command_words(command: &clap::Command) -> Vec<String> {
+
+    let mut words = Vec::new();
+
+    for arg in command.get_arguments().filter(|arg| arg.is_hide_set() == false) {
+
+        words.extend(arg.get_long().map(|long| format!("--{}", long)));
+        words.extend(arg.get_short().map(|short| format!("-{}", short)));
+    }
+
+    for subcommand in command.get_subcommands() {
+
+        words.push(subcommand.get_name().to_owned());
+        words.extend(command_words(subcommand));
+    }
+
+    return words;
+}
+
+// the flags and subcommands declared by a completion script, as whole words
+#[cfg(test)]
+fn completion_words(shell_name: &str, script: &str) -> std::collections::HashSet<String> {
+
+    let mut words = std::collections::HashSet::new();
+
+    for line in script.lines().map(str::trim) {
+
+        match shell_name {
+
+            // opts="-n --verbose ls ..." per command
+            "bash" => {
+
+                if let Some(opts) = line.strip_prefix("opts=\"").and_then(|opts| opts.strip_suffix('"')) {
+
+                    words.extend(opts.split_ascii_whitespace().map(str::to_owned));
+                }
+            },
+
+            // complete -c flf ... -s v -l verbose, the subcommands as -a "ls"
+            "fish" => {
+
+                if line.starts_with("complete -c flf ") {
+
+                    let line_words = line.split_ascii_whitespace().collect::<Vec<_>>();
+
+                    for pair in line_words.windows(2) {
+
+                        match pair[0] {
+
+                            "-s" => words.insert(format!("-{}", pair[1])),
+                            "-l" => words.insert(format!("--{}", pair[1])),
+                            "-a" => words.insert(pair[1].trim_matches('"').to_owned()),
+                            _ => false
+                        };
+                    }
+                }
+            },
+
+            // '(-v --verbose)-q[only log errors]' or '*--exclude=[...]', the subcommands as 'ls:description'
+            "zsh" => {
+
+                if let Some(spec) = line.strip_prefix('\'') {
+
+                    let spec = match spec.strip_prefix('(') {
+
+                        Some(spec) => spec.split_once(')').map_or("", |(_, spec)| spec),
+                        None => spec
+                    };
+
+                    let spec = spec.trim_start_matches('*');
+                    let end = if spec.starts_with('-') { spec.find(['=', '+', '[']) } else { spec.find(':') };
+
+                    words.extend(end.map(|end| spec[..end].to_owned()));
+                }
+            },
+            _ => panic!("unsupported shell")
+        }
+    }
+
+    return words;
+}
+
+#[test]
+fn test_write_completions() {
+
+    use clap_complete::Shell;
+
+    let words = command_words(&build_named_parser());
+
+    for (shell, shell_name, check_flag) in [(Shell::Bash, "bash", "-n"), (Shell::Zsh, "zsh", "-n"), (Shell::Fish, "fish", "--no-execute")] {
+
+        let mut script = Vec::new();
+        write_completions(shell, &mut script);
+
+        let script = String::from_utf8(script).expect("non UTF-8 completion script");
+        let script_words = completion_words(shell_name, &script);
+
+        for word in &words {
+
+            assert!(script_words.contains(word), "{} missing from the {} completions", word, shell_name);
+        }
+
+        // syntax check, when the shell is installed
+        let child = std::process::Command::new(shell_name)
+            .arg(check_flag)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::piped())
+            .spawn();
+
+        if let Ok(mut child) = child {
+
+            child.stdin.take().expect("missing stdin").write_all(script.as_bytes()).expect("shell stdin write error");
+            let output = child.wait_with_output().expect("shell wait error");
+
+            assert!(output.status.success(), "invalid {} completions: {}", shell_name, String::from_utf8_lossy(&output.stderr));
+        }
+    }
+}
+
+// the words of the paragraph tags following ".TP", which name the options and the subcommands
+#[cfg(test)]
+fn man_tag_words(page: &str) -> std::collections::HashSet<String> {
+
+    let lines = page.lines().collect::<Vec<_>>();
+
+    return lines.windows(2).filter(|pair| pair[0] == ".TP")
+        .flat_map(|pair| {
+
+            // dashes are escaped and the fonts switched inline in roff
+            let tag = pair[1].replace("\\-", "-").replace("\\fB", " ").replace("\\fI", " ").replace("\\fR", " ");
+
+            return tag.split(|c: char| c.is_ascii_alphanumeric() == false && c != '-' && c != '_')
+                .filter(|word| word.is_empty() == false)
+                .map(str::to_owned)
+                .collect::<Vec<_>>();
+        })
+        .collect();
+}
+
+#[test]
+fn test_write_man_pages() {
+
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::Dir;
+
+
+    let parser = build_named_parser();
+
+    let mut page = Vec::new();
+    write_man_page(&mut page).unwrap();
+
+    let page = String::from_utf8(page).expect("non UTF-8 man page");
+
+    assert!(page.contains(".TH flf 1"));
+
+    let page_words = man_tag_words(&page);
+
+    for subcommand in parser.get_subcommands() {
+
+        let page_name = format!("{}-{}", BIN_NAME, subcommand.get_name());
+        assert!(page_words.contains(&page_name), "{} missing from the man page", page_name);
+    }
+
+    let fs = TmpFilesystem::new(&Dir::new("root"));
+    let out_dir = fs.path().join("root/man1");
+
+    write_man_pages(&out_dir).unwrap();
+
+    let mut pages = String::new();
+
+    for subcommand_name in std::iter::once(BIN_NAME.to_owned()).chain(parser.get_subcommands().map(|subcommand| format!("{}-{}", BIN_NAME, subcommand.get_name()))) {
+
+        let content = std::fs::read_to_string(out_dir.join(format!("{}.1", subcommand_name))).expect("missing man page");
+
+        assert!(content.contains(&format!(".TH {} 1", subcommand_name)));
+        pages.push_str(&content);
+    }
+
+    let pages_words = man_tag_words(&pages);
+
+    // the subcommands are named by their page
+    for word in command_words(&parser) {
+
+        let word = if word.starts_with('-') { word } else { format!("{}-{}", BIN_NAME, word) };
+        assert!(pages_words.contains(&word), "{} missing from the man pages", word);
+    }
+}
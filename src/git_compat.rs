@@ -0,0 +1,358 @@
+#![cfg(test)]
+
+// compares the tool against "git check-ignore" on random trees
+//
+// kept in the crate instead of "tests/" since there is no library target, and the comparison needs the ignore node chain
+// and the flattened patterns, not only the output of the binary
+// the tests need git, so they're ignored by default, run them with "cargo test -- --ignored"
+
+use std::collections::HashSet;
+use std::path::Path;
+use crate::filesystem::tmp_filesystem::TmpFilesystem;
+use crate::filesystem::template::{Dir, File};
+
+
+
+const NAME_POOL: &[&str] = &["a", "b", "c", "d.log", "e.txt", "#x", "!y", "b[1]"];
+
+const PATTERN_POOL: &[&str] = &[
+    "a", "/a", "a/", "/a/", "!a", "b", "!b", "!/b/", "c", "!c",
+    "*.log", "!*.log", "!d.log", "*.txt", "d*", "?", "[ab]", "b\\[1\\]",
+    "b/c", "/b/c", "**/c", "**/b/c", "a/**", "a/**/c", "*", "!e.txt",
+    "\\#x", "\\!y", "!\\!y", "c/", "!c/"
+];
+
+
+#[derive(Clone, Debug)]
+struct TreeSpec {
+
+    name: String,
+    ignore_patterns: Option<Vec<String>>,
+    files: Vec<String>,
+    dirs: Vec<TreeSpec>
+}
+
+impl TreeSpec {
+
+    fn random(name: &str, rng: &mut Rng, depth: usize) -> Self {
+
+        let mut names = NAME_POOL.to_vec();
+        rng.shuffle(&mut names);
+
+        let file_count = rng.below(3);
+        let dir_count = if depth < 3 { rng.below(3) } else { 0 };
+
+        let files = names.iter().take(file_count).map(|name| name.to_string()).collect();
+        let dirs = names.iter().skip(file_count).take(dir_count).map(|name| Self::random(name, rng, depth + 1)).collect();
+
+        let ignore_patterns = match rng.below(2) {
+
+            0 => None,
+            _ => Some((0..=rng.below(4)).map(|_| PATTERN_POOL[rng.below(PATTERN_POOL.len())].to_owned()).collect())
+        };
+
+        return Self{name: name.to_owned(), ignore_patterns, files, dirs};
+    }
+
+    fn to_template(&self) -> Dir {
+
+        let mut dir = Dir::new(&self.name);
+
+        if let Some(ref patterns) = self.ignore_patterns {
+
+            dir = dir.add_file(File::new_gitignore(&patterns.iter().map(String::as_str).collect::<Vec<_>>()));
+        }
+
+        for file in &self.files {
+
+            dir = dir.add_file(File::new_empty(file));
+        }
+
+        for child_dir in &self.dirs {
+
+            dir = dir.add_dir(child_dir.to_template());
+        }
+
+        return dir;
+    }
+
+    // builder code of the equivalent template, to be pasted in a regression test
+    fn to_code(&self, indent: usize) -> String {
+
+        let padding = " ".repeat(indent);
+        let mut code = format!("Dir::new({:?})", self.name);
+
+        if let Some(ref patterns) = self.ignore_patterns {
+
+            code += &format!("\n{}.add_file(File::new_gitignore(&{:?}))", padding, patterns);
+        }
+
+        for file in &self.files {
+
+            code += &format!("\n{}.add_file(File::new_empty({:?}))", padding, file);
+        }
+
+        for child_dir in &self.dirs {
+
+            code += &format!("\n{}.add_dir({})", padding, child_dir.to_code(indent + 4));
+        }
+
+        return code;
+    }
+
+    // relative paths of every entry below this directory
+    fn paths(&self, prefix: &str, paths: &mut Vec<String>) {
+
+        if self.ignore_patterns.is_some() {
+
+            paths.push(format!("{}.gitignore", prefix));
+        }
+
+        for file in &self.files {
+
+            paths.push(format!("{}{}", prefix, file));
+        }
+
+        for child_dir in &self.dirs {
+
+            let child_prefix = format!("{}{}/", prefix, child_dir.name);
+
+            paths.push(child_prefix.trim_end_matches('/').to_owned());
+            child_dir.paths(&child_prefix, paths);
+        }
+    }
+
+    // every spec with exactly one element less than this one
+    fn shrink_candidates(&self) -> Vec<TreeSpec> {
+
+        let mut candidates = Vec::new();
+
+        for idx in 0..self.dirs.len() {
+
+            let mut candidate = self.clone();
+            candidate.dirs.remove(idx);
+            candidates.push(candidate);
+        }
+
+        for idx in 0..self.files.len() {
+
+            let mut candidate = self.clone();
+            candidate.files.remove(idx);
+            candidates.push(candidate);
+        }
+
+        if let Some(ref patterns) = self.ignore_patterns {
+
+            let mut candidate = self.clone();
+            candidate.ignore_patterns = None;
+            candidates.push(candidate);
+
+            for idx in 0..patterns.len() {
+
+                let mut candidate = self.clone();
+                candidate.ignore_patterns.as_mut().unwrap().remove(idx);
+                candidates.push(candidate);
+            }
+        }
+
+        for (idx, child_dir) in self.dirs.iter().enumerate() {
+
+            for child_candidate in child_dir.shrink_candidates() {
+
+                let mut candidate = self.clone();
+                candidate.dirs[idx] = child_candidate;
+                candidates.push(candidate);
+            }
+        }
+
+        return candidates;
+    }
+
+    fn with_flattened_patterns(&self, patterns: Vec<String>) -> Self {
+
+        fn empty_ignore_files(spec: &mut TreeSpec) {
+
+            if spec.ignore_patterns.is_some() {
+
+                spec.ignore_patterns = Some(Vec::new());
+            }
+
+            spec.dirs.iter_mut().for_each(empty_ignore_files);
+        }
+
+        // nested ignore files are kept empty, so the set of paths doesn't change
+        let mut spec = self.clone();
+        empty_ignore_files(&mut spec);
+        spec.ignore_patterns = Some(patterns);
+
+        return spec;
+    }
+}
+
+
+// xorshift64, the trees must be reproducible from the seed
+struct Rng(u64);
+
+impl Rng {
+
+    fn below(&mut self, limit: usize) -> usize {
+
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        return (self.0 % limit as u64) as usize;
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+
+        for idx in (1..items.len()).rev() {
+
+            items.swap(idx, self.below(idx + 1));
+        }
+    }
+}
+
+
+fn git(repository: &Path, args: &[&str], stdin: &[u8]) -> Vec<u8> {
+
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    // isolated from the user and system configuration
+    let mut child = Command::new("git")
+        .args(["-c", "core.excludesFile=/dev/null"])
+        .args(args)
+        .current_dir(repository)
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("git execution failed");
+
+    child.stdin.take().unwrap().write_all(stdin).expect("git stdin write failed");
+    let output = child.wait_with_output().expect("git execution failed");
+
+    // "check-ignore" exits with 1 when no path is ignored
+    assert!(output.status.code().is_some_and(|code| code <= 1), "git failed, error: {}", String::from_utf8_lossy(&output.stderr));
+
+    return output.stdout;
+}
+
+fn git_ignored_paths(spec: &TreeSpec, paths: &[String]) -> HashSet<String> {
+
+    let fs = TmpFilesystem::new(&spec.to_template());
+    let repository = fs.path().join(&spec.name);
+
+    git(&repository, &["init", "--quiet"], b"");
+
+    let stdin = paths.iter().flat_map(|path| path.bytes().chain([0])).collect::<Vec<_>>();
+    let stdout = git(&repository, &["check-ignore", "--no-index", "--stdin", "-z"], &stdin);
+
+    return stdout.split(|byte| *byte == 0).filter(|path| path.is_empty() == false).map(|path| String::from_utf8(path.to_owned()).unwrap()).collect();
+}
+
+
+// returns a description of every path where git, the ignore node chain and the flattened file disagree
+fn find_divergences(spec: &TreeSpec) -> Vec<String> {
+
+    let mut paths = Vec::new();
+    spec.paths("", &mut paths);
+
+    let fs = TmpFilesystem::new(&spec.to_template());
+    let root = fs.path().join(&spec.name);
+
+    // flattened patterns, relative to the repository root
+    let root_prefix = String::from_utf8(crate::absolute_ignore::escape_path_prefix(root.to_str().unwrap().as_bytes())).unwrap();
+    let flattened_patterns = crate::job::flatten(&crate::file_system::OsFileSystem, &root, ".gitignore", crate::job::FlattenOptions::default()).into_iter()
+        .map(|pattern| pattern.into_string().unwrap())
+        .map(|pattern| match pattern.strip_prefix('!') {
+
+            Some(pattern) => format!("!{}", pattern.strip_prefix(&root_prefix).expect("pattern outside of the root")),
+            None => pattern.strip_prefix(&root_prefix).expect("pattern outside of the root").to_owned()
+        })
+        .collect::<Vec<_>>();
+
+    let git_ignored = git_ignored_paths(spec, &paths);
+    let flattened_ignored = git_ignored_paths(&spec.with_flattened_patterns(flattened_patterns), &paths);
+
+    let mut divergences = Vec::new();
+
+    for path in &paths {
+
+        let git = git_ignored.contains(path);
+        let flattened = flattened_ignored.contains(path);
+        let chain = crate::explain::explain_path(&crate::file_system::OsFileSystem, &root, &root.join(path), ".gitignore", None).expect("unexpected explain error").is_ignored();
+
+        if (git == chain && git == flattened) == false {
+
+            divergences.push(format!("path: {}, git: {}, ignore node chain: {}, flattened: {}", path, git, chain, flattened));
+        }
+    }
+
+    return divergences;
+}
+
+fn minimize_divergent_spec(mut spec: TreeSpec) -> TreeSpec {
+
+    'shrinking: loop {
+
+        for candidate in spec.shrink_candidates() {
+
+            if find_divergences(&candidate).is_empty() == false {
+
+                spec = candidate;
+                continue 'shrinking;
+            }
+        }
+
+        return spec;
+    }
+}
+
+#[track_caller]
+fn assert_agrees_with_git(spec: TreeSpec) {
+
+    if find_divergences(&spec).is_empty() {
+
+        return;
+    }
+
+    let minimal_spec = minimize_divergent_spec(spec);
+    let divergences = find_divergences(&minimal_spec);
+
+    panic!("divergence from git check-ignore\n{}\n\nminimal template:\n{}", divergences.join("\n"), minimal_spec.to_code(4));
+}
+
+
+#[test]
+#[ignore = "needs git, run with \"cargo test -- --ignored\""]
+fn test_git_check_ignore_documented_tree() {
+
+    let spec = TreeSpec{
+        name: "repo".to_owned(),
+        ignore_patterns: Some(vec!["*.log".to_owned(), "/b/".to_owned(), "!important.log".to_owned(), "a/**/c".to_owned()]),
+        files: vec!["d.log".to_owned(), "important.log".to_owned()],
+        dirs: vec![
+            TreeSpec{name: "a".to_owned(), ignore_patterns: Some(vec!["!d.log".to_owned()]), files: vec!["d.log".to_owned()], dirs: vec![
+                TreeSpec{name: "x".to_owned(), ignore_patterns: None, files: vec!["c".to_owned()], dirs: vec![]}
+            ]},
+            TreeSpec{name: "b".to_owned(), ignore_patterns: Some(vec!["!e.txt".to_owned()]), files: vec!["e.txt".to_owned()], dirs: vec![]}
+        ]
+    };
+
+    assert_agrees_with_git(spec);
+}
+
+#[test]
+#[ignore = "needs git, run with \"cargo test -- --ignored\""]
+fn test_git_check_ignore_random_trees() {
+
+    for seed in 1..=40_u64 {
+
+        let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        assert_agrees_with_git(TreeSpec::random("repo", &mut rng, 0));
+    }
+}
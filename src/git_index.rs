@@ -0,0 +1,91 @@
+use std::path::{Path, PathBuf};
+
+
+
+// ignore files committed or staged in the repository index, relative to the root
+pub fn tracked_ignore_files(root: &Path, ignore_file_name: &str) -> anyhow::Result<Vec<PathBuf>> {
+
+    // "-z" keeps the names unquoted, non-UTF-8 bytes included
+    let output = std::process::Command::new("git")
+        .args(["ls-files", "--cached", "-z"])
+        .current_dir(root)
+        .output()
+        .map_err(|err| anyhow::anyhow!("git execution failed, error: {}", err))?;
+
+    if output.status.success() == false {
+
+        anyhow::bail!("git ls-files failed, error: {}", String::from_utf8_lossy(&output.stderr).trim_end());
+    }
+
+    let paths = output.stdout.split(|byte| *byte == 0)
+        .filter(|path| path.is_empty() == false)
+        .map(|path| crate::absolute_ignore::bytes_to_os_string(path.to_owned()).map(PathBuf::from))
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+    let files = paths.into_iter()
+        .filter(|path| path.file_name().is_some_and(|name| name == ignore_file_name))
+
+        // deleted from the working tree, a walk wouldn't find it either
+        .filter(|path| root.join(path).is_file())
+        .collect();
+
+    return Ok(files);
+}
+
+#[test]
+fn test_tracked_ignore_files() {
+
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
+    use crate::job::OutputOrder;
+
+
+    if std::process::Command::new("git").arg("--version").output().is_ok_and(|output| output.status.success()) == false {
+
+        eprintln!("git isn't installed, skipping");
+        return;
+    }
+
+    let fs_template = Dir::new("repo")
+        .add_file(File::new_gitignore(&["*.log"]))
+        .add_dir(Dir::new("a")
+            .add_file(File::new_gitignore(&["tracked"]))
+            .add_dir(Dir::new("scratch")
+                .add_file(File::new_gitignore(&["untracked"]))))
+        .add_dir(Dir::new("b")
+            .add_file(File::new_gitignore(&["deleted"])))
+        .add_dir(Dir::new("c")
+            .add_file(File::new("other", b"tracked but not an ignore file".to_vec())));
+
+    let fs = TmpFilesystem::new(&fs_template);
+    let root = fs.path().join("repo");
+
+    let git = |args: &[&str]| {
+
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(&root)
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .output()
+            .expect("git execution failed");
+
+        assert!(output.status.success(), "git failed, error: {}", String::from_utf8_lossy(&output.stderr));
+    };
+
+    git(&["init", "--quiet"]);
+    git(&["add", ".gitignore", "a/.gitignore", "b/.gitignore", "c/other"]);
+    std::fs::remove_file(root.join("b/.gitignore")).unwrap();
+
+    assert_eq!(tracked_ignore_files(&root, ".gitignore").unwrap(), [PathBuf::from(".gitignore"), PathBuf::from("a/.gitignore")]);
+
+    // a subdirectory of the repository lists only its own files
+    assert_eq!(tracked_ignore_files(&root.join("a"), ".gitignore").unwrap(), [PathBuf::from(".gitignore")]);
+
+    let files = tracked_ignore_files(&root, ".gitignore").unwrap();
+    let patterns = crate::files_from::flatten_files(&root, &files, false, OutputOrder::DepthFirst, None, None).into_iter()
+        .map(|pattern| pattern.into_string().unwrap().replacen(root.to_str().unwrap(), "", 1))
+        .collect::<Vec<_>>();
+
+    assert_eq!(patterns, ["/**/*.log", "/a/**/tracked"]);
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use crate::file_system::{EntryType, FileSystem};
+
+
+
+struct TreeEntry {
+
+    entry_type: EntryType,
+    object: String
+}
+
+// directories and files of a git tree-ish, the paths are the ones they would have once checked out under the root
+pub struct GitTreeFileSystem {
+
+    root: PathBuf,
+    entries: HashMap<PathBuf, TreeEntry>,
+    child_entries: HashMap<PathBuf, Vec<PathBuf>>
+}
+
+impl GitTreeFileSystem {
+
+    // the root must be a directory of the repository, the tree is the one of the same directory at the given revision
+    pub fn new(root: &Path, rev: &str) -> anyhow::Result<Self> {
+
+        // a "./" path is relative to the working directory
+        let mut tree_ish = OsString::from(rev);
+        tree_ish.push(":./");
+
+        // without "--full-tree", the listing would be limited to the working directory prefix a second time
+        let args = ["ls-tree", "-r", "-t", "-z", "--full-tree"].map(OsString::from);
+        let output = run_git(root, &[&args[..], &[tree_ish]].concat())?;
+
+        let mut entries = HashMap::new();
+        let mut child_entries = HashMap::<PathBuf, Vec<PathBuf>>::new();
+
+        entries.insert(root.to_owned(), TreeEntry{entry_type: EntryType::Dir, object: String::new()});
+        child_entries.insert(root.to_owned(), Vec::new());
+
+        for line in output.split(|byte| *byte == 0).filter(|line| line.is_empty() == false) {
+
+            let (path, entry) = parse_ls_tree_line(line).ok_or_else(|| anyhow::anyhow!("invalid git ls-tree line: {}", String::from_utf8_lossy(line)))?;
+            let path = root.join(path);
+
+            if entry.entry_type == EntryType::Dir {
+
+                child_entries.entry(path.clone()).or_default();
+            }
+
+            child_entries.entry(path.parent().expect("invalid tree path").to_owned()).or_default().push(path.clone());
+            entries.insert(path, entry);
+        }
+
+        child_entries.values_mut().for_each(|children| children.sort());
+
+        return Ok(Self{root: root.to_owned(), entries, child_entries});
+    }
+}
+
+impl FileSystem for GitTreeFileSystem {
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<(PathBuf, EntryType)>> {
+
+        let children = self.child_entries.get(path).ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, format!("missing tree: {}", path.display())))?;
+
+        return Ok(children.iter().map(|child| (child.clone(), self.entries[child].entry_type)).collect());
+    }
+
+    fn read_file(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+
+        let Some(entry) = self.entries.get(path).filter(|entry| entry.entry_type == EntryType::File) else {
+
+            return Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("missing blob: {}", path.display())));
+        };
+
+        let args = [OsString::from("cat-file"), OsString::from("blob"), OsString::from(&entry.object)];
+
+        return run_git(&self.root, &args).map_err(std::io::Error::other);
+    }
+
+    // symlinks are stored as blobs, they can't be followed
+    fn entry_type(&self, path: &Path) -> Option<EntryType> {
+
+        return self.entries.get(path).map(|entry| entry.entry_type);
+    }
+}
+
+
+// "<mode> SP <type> SP <object> TAB <path>"
+fn parse_ls_tree_line(line: &[u8]) -> Option<(PathBuf, TreeEntry)> {
+
+    let tab_idx = line.iter().position(|byte| *byte == b'\t')?;
+    let (info, path) = (std::str::from_utf8(&line[..tab_idx]).ok()?, &line[tab_idx + 1..]);
+
+    let mut fields = info.split(' ');
+    let (mode, object_type, object) = (fields.next()?, fields.next()?, fields.next()?);
+
+    let entry_type = match (object_type, mode) {
+
+        ("tree", _) => EntryType::Dir,
+        ("blob", "120000") => EntryType::Symlink,
+        ("blob", _) => EntryType::File,
+
+        // submodules
+        _ => EntryType::Other
+    };
+
+    let path = PathBuf::from(crate::absolute_ignore::bytes_to_os_string(path.to_owned()).ok()?);
+
+    return Some((path, TreeEntry{entry_type, object: object.to_owned()}));
+}
+
+#[test]
+fn test_parse_ls_tree_line() {
+
+    #[track_caller]
+    fn assert_parse(line: &str, expected: Option<(&str, EntryType, &str)>) {
+
+        let result = parse_ls_tree_line(line.as_bytes()).map(|(path, entry)| (path, entry.entry_type, entry.object));
+        assert_eq!(result, expected.map(|(path, entry_type, object)| (PathBuf::from(path), entry_type, object.to_owned())));
+    }
+
+    assert_parse("100644 blob 1234\t.gitignore", Some((".gitignore", EntryType::File, "1234")));
+    assert_parse("100755 blob 1234\ta/run.sh", Some(("a/run.sh", EntryType::File, "1234")));
+    assert_parse("040000 tree 5678\ta b", Some(("a b", EntryType::Dir, "5678")));
+    assert_parse("120000 blob 9abc\tlink", Some(("link", EntryType::Symlink, "9abc")));
+    assert_parse("160000 commit def0\tsubmodule", Some(("submodule", EntryType::Other, "def0")));
+    assert_parse("100644 blob 1234", None);
+}
+
+
+fn run_git(dir: &Path, args: &[OsString]) -> anyhow::Result<Vec<u8>> {
+
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|err| anyhow::anyhow!("git execution failed, error: {}", err))?;
+
+    if output.status.success() == false {
+
+        anyhow::bail!("git failed, error: {}", String::from_utf8_lossy(&output.stderr).trim_end());
+    }
+
+    return Ok(output.stdout);
+}
+
+#[test]
+fn test_git_tree_flatten() {
+
+    use crate::file_system::OsFileSystem;
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
+    use crate::job::{self, FlattenOptions};
+
+
+    if std::process::Command::new("git").arg("--version").output().is_ok_and(|output| output.status.success()) == false {
+
+        eprintln!("git isn't installed, skipping");
+        return;
+    }
+
+    let fs_template = Dir::new("repo")
+        .add_file(File::new_gitignore(&["build/", "*.log"]))
+        .add_dir(Dir::new("a")
+            .add_file(File::new_gitignore(&["foo", "!bar"]))
+            .add_dir(Dir::new("b")
+                .add_file(File::new_gitignore(&["baz"]))))
+        .add_dir(Dir::new("build")
+            .add_file(File::new_gitignore(&["hidden"])));
+
+    let fs = TmpFilesystem::new(&fs_template);
+    let root = fs.path().join("repo");
+
+    let git = |args: &[&str]| {
+
+        let output = std::process::Command::new("git")
+            .args(["-c", "user.name=flf", "-c", "user.email=flf@localhost"])
+            .args(args)
+            .current_dir(&root)
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .output()
+            .expect("git execution failed");
+
+        assert!(output.status.success(), "git failed, error: {}", String::from_utf8_lossy(&output.stderr));
+    };
+
+    git(&["init", "--quiet"]);
+    git(&["add", "."]);
+    git(&["commit", "--quiet", "-m", "first"]);
+
+    let committed_patterns = job::flatten(&OsFileSystem, &root, ".gitignore", FlattenOptions{keep_comments: true, ..Default::default()});
+
+    // working tree changes don't affect the committed tree
+    std::fs::write(root.join("a/.gitignore"), "changed\n").unwrap();
+    std::fs::create_dir(root.join("c")).unwrap();
+    std::fs::write(root.join("c/.gitignore"), "new\n").unwrap();
+
+    let tree_fs = GitTreeFileSystem::new(&root, "HEAD").unwrap();
+    assert_eq!(job::flatten(&tree_fs, &root, ".gitignore", FlattenOptions{keep_comments: true, ..Default::default()}), committed_patterns);
+
+    // subdirectory of the repository
+    let tree_fs = GitTreeFileSystem::new(&root.join("a"), "HEAD").unwrap();
+    assert_eq!(tree_fs.read_dir(&root.join("a")).unwrap(), [(root.join("a/.gitignore"), EntryType::File), (root.join("a/b"), EntryType::Dir)]);
+    assert_eq!(tree_fs.read_file(&root.join("a/.gitignore")).unwrap(), b"foo\n!bar");
+    assert_eq!(tree_fs.entry_type(&root.join("a/b/.gitignore")), Some(EntryType::File));
+    assert_eq!(tree_fs.entry_type(&root.join("c")), None);
+
+    assert!(GitTreeFileSystem::new(&root, "missing-rev").is_err());
+}
@@ -3,7 +3,10 @@
 use std::sync::Arc;
 
 // extern
-use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder, Glob};
+
+// local
+use crate::file_system::{EntryType, FileSystem};
 
 
 
@@ -16,16 +19,38 @@ pub struct IgnoreNode {
 
 impl IgnoreNode {
 
-    pub fn new(path: &std::path::Path, parent: Option<Arc<IgnoreNode>>) -> Arc<Self> {
-
-        assert!(path.is_file());
+    pub fn new(fs: &dyn FileSystem, path: &std::path::Path, parent: Option<Arc<IgnoreNode>>) -> Arc<Self> {
 
-        let mut builder = GitignoreBuilder::new(path.parent().expect("invalid path"));
-        
-        if let Some(error) = builder.add(path) {
+        let content = fs.read_file(path).unwrap_or_else(|err| {
 
-            eprintln!("invalid file list, error: {:?}", error);
+            tracing::error!(path = %path.display(), error = %err, "ignore file reading error");
             std::process::exit(1);
+        });
+
+        // decoded the same way as the flattened patterns, instead of "GitignoreBuilder::add"
+        let lines = crate::absolute_ignore::decode_ignore_file(&content);
+
+        return Self::from_lines(path, &crate::absolute_ignore::matcher_lines(&lines), parent);
+    }
+
+    // builds the node from the already decoded lines of the ignore file at the given path
+    pub fn from_lines(path: &std::path::Path, lines: &[String], parent: Option<Arc<IgnoreNode>>) -> Arc<Self> {
+
+        return Self::from_source_lines(path.parent().expect("invalid path"), path, lines, parent);
+    }
+
+    // patterns relative to the given directory, the source names their origin in the matched globs and may not be a file
+    pub fn from_source_lines(dir: &std::path::Path, source: &std::path::Path, lines: &[String], parent: Option<Arc<IgnoreNode>>) -> Arc<Self> {
+
+        let mut builder = GitignoreBuilder::new(dir);
+
+        for line in lines {
+
+            if let Err(error) = builder.add_line(Some(source.to_owned()), line) {
+
+                tracing::error!(path = %source.display(), error = ?error, "invalid file list");
+                std::process::exit(1);
+            }
         }
         
         let matcher = builder.build().expect("matcher build unexpected error");
@@ -33,23 +58,47 @@ impl IgnoreNode {
         return Arc::new(IgnoreNode{matcher, parent});
     }
 
+    // loads the ignore file of the given directory if it exists, otherwise the parent context is kept
+    pub fn from_dir(fs: &dyn FileSystem, dir: &std::path::Path, ignore_file_name: &str, parent: Option<Arc<IgnoreNode>>) -> Option<Arc<Self>> {
+
+        let ignore_file_path = dir.join(ignore_file_name);
+
+        if fs.entry_type(&ignore_file_path) == Some(EntryType::File) {
+
+            return Some(Self::new(fs, &ignore_file_path, parent));
+        }
+
+        return parent;
+    }
+
+    // number of ignore files in the chain
+    pub fn depth(self: &Arc<Self>) -> usize {
+
+        return 1 + self.parent.as_ref().map_or(0, |parent| parent.depth());
+    }
+
     pub fn matches(self: &Arc<Self>, path: &std::path::Path) -> bool {
 
+        return self.matched(path, true).is_ignore();
+    }
+
+    // returns the deciding match of the closest ignore file, along with its glob
+    pub fn matched(self: &Arc<Self>, path: &std::path::Path, is_dir: bool) -> ignore::Match<&Glob> {
+
         let mut node = self;
 
         loop {
 
-            match node.matcher.matched(path, true) {
+            match node.matcher.matched(path, is_dir) {
                 
                 ignore::Match::None => {},
-                ignore::Match::Ignore(_) => { return true; },
-                ignore::Match::Whitelist(_) => { return false; },
+                decided_match => { return decided_match; }
             }
 
             match node.parent {
 
                 Some(ref parent) => node = parent,
-                None => { return false; }
+                None => { return ignore::Match::None; }
             }
         }
     }
@@ -59,7 +108,7 @@ impl IgnoreNode {
 fn test_ignore_node() {
 
     use std::path::Path;
-    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::file_system::MemoryFileSystem;
     use crate::filesystem::template::{File, Dir};
     
     let dir_template = Dir::new("dir")
@@ -72,10 +121,10 @@ fn test_ignore_node() {
             .add_dir(Dir::new("foo_dir"))
             .add_dir(Dir::new("bar_dir")));
 
-    let filesystem = TmpFilesystem::new(&dir_template);
+    let filesystem = MemoryFileSystem::from_template(Path::new("/"), &dir_template);
 
-    let matcher_dir = IgnoreNode::new(&filesystem.path().join("dir/.gitignore"), None);
-    let matcher_sub_dir = IgnoreNode::new(&filesystem.path().join("dir/sub_dir/.gitignore"), Some(matcher_dir.clone()));
+    let matcher_dir = IgnoreNode::new(&filesystem, Path::new("/dir/.gitignore"), None);
+    let matcher_sub_dir = IgnoreNode::new(&filesystem, Path::new("/dir/sub_dir/.gitignore"), Some(matcher_dir.clone()));
 
     assert_eq!(matcher_dir.matches(Path::new("ignore_dir1")), true);
     assert_eq!(matcher_dir.matches(Path::new("ignore_dir2")), true);
@@ -84,4 +133,68 @@ fn test_ignore_node() {
 
     assert_eq!(matcher_sub_dir.matches(Path::new("foo_dir")), true);
     assert_eq!(matcher_sub_dir.matches(Path::new("bar_dir")), false);
+
+    assert_eq!(matcher_dir.depth(), 1);
+    assert_eq!(matcher_sub_dir.depth(), 2);
+}
+
+#[test]
+fn test_ignore_node_matched() {
+
+    use std::path::Path;
+    use crate::file_system::MemoryFileSystem;
+    use crate::filesystem::template::{File, Dir};
+
+    let dir_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["foo*", "bar/"]))
+        .add_dir(Dir::new("sub_dir")
+            .add_file(File::new_gitignore(&["!foo_white"])))
+        .add_dir(Dir::new("empty_dir"));
+
+    let filesystem = MemoryFileSystem::from_template(Path::new("/"), &dir_template);
+    let dir_path = Path::new("/dir");
+
+    let matcher_dir = IgnoreNode::from_dir(&filesystem, dir_path, ".gitignore", None).expect("missing ignore file");
+    let matcher_sub_dir = IgnoreNode::from_dir(&filesystem, &dir_path.join("sub_dir"), ".gitignore", Some(matcher_dir.clone())).expect("missing ignore file");
+    let matcher_empty_dir = IgnoreNode::from_dir(&filesystem, &dir_path.join("empty_dir"), ".gitignore", Some(matcher_dir.clone())).expect("missing parent context");
+
+    assert!(Arc::ptr_eq(&matcher_empty_dir, &matcher_dir));
+    assert!(IgnoreNode::from_dir(&filesystem, &dir_path.join("empty_dir"), ".gitignore", None).is_none());
+
+    let matched_glob = |node: &Arc<IgnoreNode>, path: &str, is_dir: bool| match node.matched(Path::new(path), is_dir) {
+
+        ignore::Match::None => None,
+        ignore::Match::Ignore(glob) => Some((false, glob.original().to_owned(), glob.from().map(Path::to_owned))),
+        ignore::Match::Whitelist(glob) => Some((true, glob.original().to_owned(), glob.from().map(Path::to_owned)))
+    };
+
+    let dir_ignore_file = Some(dir_path.join(".gitignore"));
+    let sub_dir_ignore_file = Some(dir_path.join("sub_dir/.gitignore"));
+
+    assert_eq!(matched_glob(&matcher_dir, "foo_file", false), Some((false, "foo*".to_owned(), dir_ignore_file.clone())));
+    assert_eq!(matched_glob(&matcher_dir, "bar", true), Some((false, "bar/".to_owned(), dir_ignore_file.clone())));
+    assert_eq!(matched_glob(&matcher_dir, "bar", false), None);
+    assert_eq!(matched_glob(&matcher_sub_dir, "foo_white", false), Some((true, "!foo_white".to_owned(), sub_dir_ignore_file)));
+    assert_eq!(matched_glob(&matcher_sub_dir, "foo_black", false), Some((false, "foo*".to_owned(), dir_ignore_file)));
+}
+
+#[test]
+fn test_ignore_node_encoding() {
+
+    use std::path::Path;
+    use crate::file_system::MemoryFileSystem;
+    use crate::filesystem::template::{File, Dir};
+
+    let dir_template = Dir::new("dir")
+        .add_file(File::new(".gitignore", b"\xef\xbb\xbffoo\r\nbar\xff\r\n!foo_white\r\n".to_vec()));
+
+    let filesystem = MemoryFileSystem::from_template(Path::new("/"), &dir_template);
+    let matcher = IgnoreNode::new(&filesystem, Path::new("/dir/.gitignore"), None);
+
+    assert_eq!(matcher.matches(Path::new("foo")), true);
+    assert_eq!(matcher.matches(Path::new("foo_white")), false);
+
+    // git matches "bar\xff" against the raw file names, the lossily decoded pattern would match another name
+    assert_eq!(matcher.matches(Path::new("bar\u{fffd}")), false);
+    assert_eq!(matcher.matches(Path::new("bar")), false);
 }
@@ -1,10 +1,372 @@
 
 use std::sync::Arc;
 use crate::absolute_ignore;
+use crate::file_system::{EntryType, FileSystem};
 use crate::ignore_node::IgnoreNode;
+use crate::stats::{FlattenStats, StatsFileSystem};
 
 
 
+// every order keeps the ignore files of parent directories before the ones of their descendants, as required by negations
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputOrder {
+
+    // order in which the job stack processes the directories
+    Traversal,
+
+    // byte order of the directory paths
+    Path,
+
+    // the command line default
+    #[default]
+    DepthFirst,
+    BreadthFirst
+}
+
+
+// patterns that don't come from the tree, applied to all of it with a lower precedence than its ignore files
+#[derive(Default)]
+pub struct RootRules {
+
+    context: Option<Arc<IgnoreNode>>,
+    sections: Vec<Vec<std::ffi::OsString>>
+}
+
+impl RootRules {
+
+    pub fn context(&self) -> Option<Arc<IgnoreNode>> {
+
+        return self.context.clone();
+    }
+
+    // written to the output as a section of their own, the source is named by the "--keep-comments" header
+    pub fn add_patterns(&mut self, root: &std::path::Path, source: &std::path::Path, patterns: &[String], keep_comments: bool) {
+
+        let lines = patterns.iter().map(|pattern| absolute_ignore::IgnoreFileLine::from_text(pattern)).collect::<Vec<_>>();
+        self.add_lines(root, source, &lines, keep_comments);
+    }
+
+    // decoded like the ignore files of the tree, the file itself doesn't have to be part of it
+    pub fn add_patterns_from_file(&mut self, fs: &dyn FileSystem, root: &std::path::Path, path: &std::path::Path, keep_comments: bool) -> std::io::Result<()> {
+
+        let content = fs.read_file(path)?;
+        self.add_lines(root, path, &absolute_ignore::decode_ignore_file(&content), keep_comments);
+
+        return Ok(());
+    }
+
+    fn add_lines(&mut self, root: &std::path::Path, source: &std::path::Path, lines: &[absolute_ignore::IgnoreFileLine], keep_comments: bool) {
+
+        if lines.is_empty() {
+
+            return;
+        }
+
+        let section = match keep_comments {
+
+            true => absolute_ignore::annotated_patterns_from_lines(source, lines, root, root),
+            false => absolute_ignore::patterns_from_lines(source, lines, root)
+        };
+
+        self.context = Some(IgnoreNode::from_source_lines(root, source, &absolute_ignore::matcher_lines(lines), self.context.take()));
+        self.sections.push(section);
+    }
+
+    // only prunes the traversal, a directory pattern without a trailing "/" gets one
+    pub fn add_excluded_dirs(&mut self, root: &std::path::Path, source: &std::path::Path, dirs: &[String]) {
+
+        if dirs.is_empty() {
+
+            return;
+        }
+
+        let patterns = dirs.iter().map(|dir| if dir.ends_with('/') { dir.clone() } else { format!("{}/", dir) }).collect::<Vec<_>>();
+        self.context = Some(IgnoreNode::from_source_lines(root, source, &patterns, self.context.take()));
+    }
+
+    pub fn sections(&self) -> &[Vec<std::ffi::OsString>] {
+
+        return &self.sections;
+    }
+}
+
+// the defaults flatten the tree alone, without comments, in depth-first order
+#[derive(Default)]
+pub struct FlattenOptions<'a> {
+
+    pub keep_comments: bool,
+    pub order: OutputOrder,
+    pub root_rules: Option<&'a RootRules>,
+    pub cache: Option<&'a mut crate::cache::FlattenCache>,
+    pub stats: Option<&'a mut FlattenStats>
+}
+
+// flattens every ignore file under the root, patterns keep their order within each file
+pub fn flatten(fs: &dyn FileSystem, root: &std::path::Path, ignore_file_name: &str, options: FlattenOptions) -> Vec<std::ffi::OsString> {
+
+    let FlattenOptions{keep_comments, order, root_rules, mut cache, mut stats} = options;
+
+    let started = std::time::Instant::now();
+
+    // the IO is only timed when the stats are requested
+    let stats_fs = stats.as_ref().map(|_| StatsFileSystem::new(fs));
+    let fs: &dyn FileSystem = match stats_fs {
+
+        Some(ref stats_fs) => stats_fs,
+        None => fs
+    };
+
+    let mut sections = root_rules.map_or_else(Vec::new, |rules| rules.sections().iter().map(|section| (root.to_owned(), section.clone())).collect());
+    let first_job = Job{path: root.to_owned(), ignore_context: root_rules.and_then(RootRules::context)};
+
+    let process = &mut |job| match cache {
+
+        Some(ref mut cache) => cache.process_job(fs, root, job),
+        None => process_job(fs, root, job, ignore_file_name, keep_comments)
+    };
+
+    let peak_job_stack = walk(first_job, process, &mut |dir| {
+
+        if let Some(ref mut stats) = stats {
+
+            stats.visited_dirs += 1;
+            stats.pruned_dirs += dir.pruned_dirs;
+
+            if let Some(ref new_patterns) = dir.patterns {
+
+                stats.add_ignore_file(new_patterns, dir.ignore_context.as_ref().map_or(0, |context| context.depth()));
+            }
+        }
+
+        if let Some(new_patterns) = dir.patterns {
+
+            sections.push((dir.path, new_patterns));
+        }
+    });
+
+    if let (Some(stats), Some(stats_fs)) = (stats, stats_fs) {
+
+        stats.peak_job_stack = peak_job_stack as u64;
+        stats.io_time = stats_fs.io_time.get();
+        stats.matching_time = started.elapsed().saturating_sub(stats.io_time);
+    }
+
+    order_sections(root, &mut sections, order);
+
+    return join_sections(sections, keep_comments);
+}
+
+#[test]
+fn test_flatten_root_rules() {
+
+    use std::path::Path;
+    use crate::file_system::MemoryFileSystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["!keep.swp"]))
+        .add_dir(Dir::new("node_modules")
+            .add_file(File::new_gitignore(&["hidden"])))
+        .add_dir(Dir::new("tmp")
+            .add_file(File::new_gitignore(&["hidden"])))
+        .add_dir(Dir::new("src")
+            .add_file(File::new_gitignore(&["*.o"])));
+
+    let fs = MemoryFileSystem::from_template(Path::new("/"), &fs_template);
+    let root = Path::new("/dir");
+    let source = Path::new("/dir/flf.toml");
+
+    let mut rules = RootRules::default();
+    rules.add_patterns(root, source, &["*.swp".to_owned(), "tmp/".to_owned()], false);
+    rules.add_excluded_dirs(root, source, &["node_modules".to_owned()]);
+
+    // the configured patterns come first and prune like the root ignore file, the excluded directories aren't written
+    assert_eq!(flatten(&fs, root, ".gitignore", FlattenOptions{root_rules: Some(&rules), ..Default::default()}), [
+        "/dir/**/*.swp", "/dir/**/tmp/", "!/dir/**/keep.swp", "/dir/src/**/*.o"
+    ]);
+
+    // the ignore files of the tree take precedence
+    let context = rules.context().unwrap();
+    assert_eq!(context.matched(Path::new("/dir/a.swp"), false).is_ignore(), true);
+    assert_eq!(context.matched(Path::new("/dir/node_modules"), true).is_ignore(), true);
+
+    let root_context = IgnoreNode::from_dir(&fs, root, ".gitignore", rules.context()).unwrap();
+    assert_eq!(root_context.matched(Path::new("/dir/keep.swp"), false).is_whitelist(), true);
+
+    let mut rules = RootRules::default();
+    rules.add_patterns(root, source, &["*.swp".to_owned()], true);
+
+    assert_eq!(flatten(&fs, root, ".gitignore", FlattenOptions{keep_comments: true, order: OutputOrder::BreadthFirst, root_rules: Some(&rules), ..Default::default()})[..4], [
+        "# from flf.toml", "/dir/**/*.swp", "", "# from .gitignore"
+    ]);
+}
+
+#[test]
+fn test_root_rules_from_file() {
+
+    use std::path::Path;
+    use crate::file_system::MemoryFileSystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["!keep.log"]))
+        .add_file(File::new("extra.ignore", b"\xef\xbb\xbf# extra\n*.log\n\nbuild/\n".to_vec()))
+        .add_dir(Dir::new("build")
+            .add_file(File::new_gitignore(&["hidden"])))
+        .add_dir(Dir::new("src")
+            .add_file(File::new_gitignore(&["*.o"])));
+
+    let fs = MemoryFileSystem::from_template(Path::new("/"), &fs_template);
+    let root = Path::new("/dir");
+    let extra_file = Path::new("/dir/extra.ignore");
+
+    // the file patterns prune the traversal, then the command line ones follow
+    let mut rules = RootRules::default();
+    rules.add_patterns_from_file(&fs, root, extra_file, false).unwrap();
+    rules.add_patterns(root, Path::new("command line"), &["*.tmp".to_owned()], false);
+
+    assert_eq!(flatten(&fs, root, ".gitignore", FlattenOptions{root_rules: Some(&rules), ..Default::default()}), [
+        "/dir/**/*.log", "/dir/**/build/", "/dir/**/*.tmp", "!/dir/**/keep.log", "/dir/src/**/*.o"
+    ]);
+
+    let glob = rules.context().unwrap().matched(Path::new("/dir/main.log"), false).inner().map(|glob| glob.from().map(Path::to_owned));
+    assert_eq!(glob, Some(Some(extra_file.to_owned())));
+
+    // the comments are kept as they are in the file
+    let mut rules = RootRules::default();
+    rules.add_patterns_from_file(&fs, root, extra_file, true).unwrap();
+
+    assert_eq!(flatten(&fs, root, ".gitignore", FlattenOptions{keep_comments: true, root_rules: Some(&rules), ..Default::default()})[..5], [
+        "# from extra.ignore", "# extra", "/dir/**/*.log", "", "/dir/**/build/"
+    ]);
+
+    assert!(RootRules::default().add_patterns_from_file(&fs, root, Path::new("/dir/missing"), false).is_err());
+}
+
+#[test]
+fn test_flatten_stats() {
+
+    use crate::file_system::MemoryFileSystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["# build output", "build/", "", "*.log"]))
+        .add_dir(Dir::new("a")
+            .add_file(File::new_gitignore(&["foo"]))
+            .add_dir(Dir::new("b")
+                .add_file(File::new_gitignore(&["bar", "baz"])))
+            .add_dir(Dir::new("c")))
+        .add_dir(Dir::new("build")
+            .add_file(File::new_gitignore(&["hidden"]))
+            .add_dir(Dir::new("obj")));
+
+    let fs = MemoryFileSystem::from_template(std::path::Path::new("/"), &fs_template);
+    let root = std::path::Path::new("/dir");
+
+    for keep_comments in [false, true] {
+
+        let mut stats = FlattenStats::default();
+        let patterns = flatten(&fs, root, ".gitignore", FlattenOptions{keep_comments, stats: Some(&mut stats), ..Default::default()});
+
+        assert_eq!(patterns, flatten(&fs, root, ".gitignore", FlattenOptions{keep_comments, ..Default::default()}));
+
+        assert_eq!(stats.visited_dirs, 4);
+        assert_eq!(stats.pruned_dirs, 1);
+        assert_eq!(stats.ignore_files, 3);
+        assert_eq!(stats.patterns, 5);
+        assert_eq!(stats.max_chain_depth, 3);
+        assert_eq!(stats.peak_job_stack, 2);
+
+        // the cached listings are counted the same way
+        let cache_dir = crate::filesystem::tmp_filesystem::TmpFilesystem::new(&Dir::new("cache"));
+        let mut cache = crate::cache::FlattenCache::load(&cache_dir.path().join("cache.json"), root, ".gitignore", keep_comments);
+        let mut cache_stats = FlattenStats::default();
+
+        assert_eq!(flatten(&fs, root, ".gitignore", FlattenOptions{keep_comments, cache: Some(&mut cache), stats: Some(&mut cache_stats), ..Default::default()}), patterns);
+        assert_eq!((cache_stats.visited_dirs, cache_stats.pruned_dirs, cache_stats.ignore_files, cache_stats.patterns), (4, 1, 3, 5));
+        assert_eq!((cache_stats.max_chain_depth, cache_stats.peak_job_stack), (3, 2));
+    }
+}
+
+// sections are the patterns of each ignore file, along with its directory
+pub fn order_sections(root: &std::path::Path, sections: &mut [(std::path::PathBuf, Vec<std::ffi::OsString>)], order: OutputOrder) {
+
+    let depth = |dir: &std::path::Path| dir.strip_prefix(root).expect("directory outside of the root").components().count();
+
+    match order {
+
+        OutputOrder::Traversal => {},
+        OutputOrder::Path => sections.sort_by(|(dir1, _), (dir2, _)| dir1.as_os_str().as_encoded_bytes().cmp(dir2.as_os_str().as_encoded_bytes())),
+        OutputOrder::DepthFirst => sections.sort_by(|(dir1, _), (dir2, _)| dir1.cmp(dir2)),
+        OutputOrder::BreadthFirst => sections.sort_by(|(dir1, _), (dir2, _)| depth(dir1).cmp(&depth(dir2)).then_with(|| dir1.cmp(dir2)))
+    }
+}
+
+pub fn join_sections(sections: Vec<(std::path::PathBuf, Vec<std::ffi::OsString>)>, keep_comments: bool) -> Vec<std::ffi::OsString> {
+
+    let mut patterns = Vec::new();
+
+    for (_, section_patterns) in sections {
+
+        // separate the source file sections
+        if keep_comments && patterns.is_empty() == false {
+
+            patterns.push(std::ffi::OsString::new());
+        }
+
+        patterns.extend(section_patterns);
+    }
+
+    return patterns;
+}
+
+#[test]
+fn test_flatten_order() {
+
+    use crate::file_system::{MemoryFileSystem, OsFileSystem};
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["root1", "!root2"]))
+        .add_dir(Dir::new("a")
+            .add_file(File::new_gitignore(&["a"]))
+            .add_dir(Dir::new("b")
+                .add_file(File::new_gitignore(&["a_b"]))))
+        .add_dir(Dir::new("a-c")
+            .add_file(File::new_gitignore(&["a-c"])))
+        .add_dir(Dir::new("c")
+            .add_file(File::new_gitignore(&["c"])));
+
+    let fs = MemoryFileSystem::from_template(std::path::Path::new("/"), &fs_template);
+    let root = std::path::PathBuf::from("/dir");
+
+    let flatten_names = |fs: &dyn FileSystem, root: &std::path::Path, order| flatten(fs, root, ".gitignore", FlattenOptions{order, ..Default::default()}).into_iter()
+        .map(|pattern| pattern.into_string().unwrap().rsplit('/').next().unwrap().to_owned())
+        .collect::<Vec<_>>();
+
+    assert_eq!(flatten_names(&fs, &root, OutputOrder::Traversal), ["root1", "root2", "c", "a-c", "a", "a_b"]);
+    assert_eq!(flatten_names(&fs, &root, OutputOrder::Path), ["root1", "root2", "a", "a-c", "a_b", "c"]);
+    assert_eq!(flatten_names(&fs, &root, OutputOrder::DepthFirst), ["root1", "root2", "a", "a_b", "a-c", "c"]);
+    assert_eq!(flatten_names(&fs, &root, OutputOrder::BreadthFirst), ["root1", "root2", "a", "a-c", "c", "a_b"]);
+
+    // same output on every run, and on disk
+    let tmp_fs = TmpFilesystem::new(&fs_template);
+    let tmp_root = tmp_fs.path().join("dir");
+
+    for order in [OutputOrder::Traversal, OutputOrder::Path, OutputOrder::DepthFirst, OutputOrder::BreadthFirst] {
+
+        assert_eq!(flatten(&fs, &root, ".gitignore", FlattenOptions{order, ..Default::default()}), flatten(&fs, &root, ".gitignore", FlattenOptions{order, ..Default::default()}));
+        assert_eq!(flatten_names(&OsFileSystem, &tmp_root, order), flatten_names(&fs, &root, order));
+    }
+}
+
+
 #[derive(Debug)]
 pub struct Job {
 
@@ -13,40 +375,133 @@ pub struct Job {
 }
 
 
-pub fn process_job(job: Job, push_job: &mut dyn FnMut(Job), ignore_file_name: &str) -> Option<Vec<String>> {
+// what the traversal found in a directory
+pub struct VisitedDir {
+
+    pub path: std::path::PathBuf,
+
+    // context inherited from the parent directory, and the one extended with the local ignore file
+    pub parent_context: Option<Arc<IgnoreNode>>,
+    pub ignore_context: Option<Arc<IgnoreNode>>,
+
+    pub patterns: Option<Vec<std::ffi::OsString>>,
+    pub child_dirs: Vec<std::path::PathBuf>,
 
-    let Job{ref path, mut ignore_context} = job;
+    // subdirectories excluded by the ignore context
+    pub pruned_dirs: u64
+}
+
+
+// visits the directories under the job's one that aren't excluded, in the job stack order, returns the peak stack size
+pub fn walk(job: Job, process_job: &mut dyn FnMut(Job) -> VisitedDir, visit: &mut dyn FnMut(VisitedDir)) -> usize {
+
+    let mut jobs = vec![job];
+    let mut peak_job_stack = 0;
+
+    while jobs.is_empty() == false {
+
+        peak_job_stack = peak_job_stack.max(jobs.len());
+
+        let job = jobs.pop().expect("invalid job stack size");
+        let dir = process_job(job);
+
+        jobs.extend(dir.child_dirs.iter().map(|child_dir| Job{path: child_dir.clone(), ignore_context: dir.ignore_context.clone()}));
+        visit(dir);
+    }
+
+    return peak_job_stack;
+}
+
+
+pub fn process_job(fs: &dyn FileSystem, root: &std::path::Path, job: Job, ignore_file_name: &str, keep_comments: bool) -> VisitedDir {
+
+    let Job{path, ignore_context: parent_context} = job;
+    tracing::debug!(path = %path.display(), "directory visited");
+
+    let (ignore_context, patterns) = load_local_ignore_file(fs, root, &path, parent_context.clone(), ignore_file_name.as_ref(), keep_comments);
+    let subdirs = read_dir(fs, &path).into_iter()
+        .filter(|(_, entry_type)| *entry_type == EntryType::Dir)
+        .map(|(dir, _)| dir);
+
+    let (child_dirs, pruned_dirs) = split_pruned_dirs(&ignore_context, subdirs);
+
+    return VisitedDir{path, parent_context, ignore_context, patterns, child_dirs, pruned_dirs};
+}
+
+// subdirectories that aren't excluded by the ignore context, along with the excluded count
+pub fn split_pruned_dirs(ignore_context: &Option<Arc<IgnoreNode>>, dirs: impl Iterator<Item = std::path::PathBuf>) -> (Vec<std::path::PathBuf>, u64) {
+
+    let mut child_dirs = Vec::new();
+    let mut pruned_dirs = 0;
+
+    for dir in dirs {
+
+        match is_pruned(ignore_context, &dir) {
+
+            true => pruned_dirs += 1,
+            false => child_dirs.push(dir)
+        }
+    }
+
+    return (child_dirs, pruned_dirs);
+}
+
+// whether the ignore context excludes the subdirectory, the deciding pattern is logged
+pub fn is_pruned(ignore_context: &Option<Arc<IgnoreNode>>, dir: &std::path::Path) -> bool {
+
+    let Some(matcher) = ignore_context else { return false; };
+    let ignore::Match::Ignore(glob) = matcher.matched(dir, true) else { return false; };
+
+    let source = glob.from().map_or_else(String::new, |source| source.display().to_string());
+    tracing::info!(path = %dir.display(), pattern = glob.original(), source, "directory pruned");
+
+    return true;
+}
+
+// extends the ignore context with the directory's ignore file, returning its patterns if present
+pub fn load_local_ignore_file(fs: &dyn FileSystem, root: &std::path::Path, path: &std::path::Path, mut ignore_context: Option<Arc<IgnoreNode>>, ignore_file_name: &std::ffi::OsStr, keep_comments: bool) -> (Option<Arc<IgnoreNode>>, Option<Vec<std::ffi::OsString>>) {
 
     let mut local_patterns = None;
 
     let local_gitignore_path = path.join(ignore_file_name);
-    if local_gitignore_path.is_file() {
+    if fs.entry_type(&local_gitignore_path) == Some(EntryType::File) {
 
-        ignore_context = Some(IgnoreNode::new(&local_gitignore_path, ignore_context));
-        local_patterns = Some(absolute_ignore::read_patterns_from_file(&local_gitignore_path, path));
-    }
+        let content = fs.read_file(&local_gitignore_path).unwrap_or_else(|err| walk_io_error_handler(err));
+        let lines = absolute_ignore::decode_ignore_file(&content);
 
-    
-    fn walk_io_error_handler(err: impl std::error::Error) -> ! {
-        
-        eprintln!("filesystem traversal IO error, error: {:?}", err);
-        std::process::exit(1);
+        tracing::info!(path = %local_gitignore_path.display(), lines = lines.len(), "ignore file loaded");
+
+        ignore_context = Some(IgnoreNode::from_lines(&local_gitignore_path, &absolute_ignore::matcher_lines(&lines), ignore_context));
+
+        if keep_comments {
+
+            local_patterns = Some(absolute_ignore::annotated_patterns_from_lines(&local_gitignore_path, &lines, path, root));
+        }
+        else {
+
+            local_patterns = Some(absolute_ignore::patterns_from_lines(&local_gitignore_path, &lines, path));
+        }
     }
 
-    let dir_walker = std::fs::read_dir(path).unwrap_or_else(|err| walk_io_error_handler(err));
+    return (ignore_context, local_patterns);
+}
+
+fn walk_io_error_handler(err: impl std::error::Error) -> ! {
     
-    dir_walker.into_iter()
-        .map(|entry| entry.unwrap_or_else(|err| walk_io_error_handler(err)))
-        .filter(|entry| entry.file_type().unwrap_or_else(|err| walk_io_error_handler(err)).is_dir())
-        .filter(|dir| ignore_context.is_none() || ignore_context.as_ref().is_some_and(|matcher| matcher.matches(&dir.path()) == false))
-        .for_each(|dir| push_job(Job{path: dir.path().to_owned(), ignore_context: ignore_context.clone()}));
+    tracing::error!(error = ?err, "filesystem traversal IO error");
+    std::process::exit(1);
+}
+
+// the traversal can't go on without the listing
+pub fn read_dir(fs: &dyn FileSystem, path: &std::path::Path) -> Vec<(std::path::PathBuf, EntryType)> {
 
-    return local_patterns;
+    return fs.read_dir(path).unwrap_or_else(|err| walk_io_error_handler(err));
 }
 
 #[test]
 fn test_job_processing() {
 
+    use crate::file_system::OsFileSystem;
     use crate::filesystem::tmp_filesystem::TmpFilesystem;
     use crate::filesystem::template::{Dir, File};
 
@@ -59,21 +514,20 @@ fn test_job_processing() {
 
     let fs = TmpFilesystem::new(&fs_template);
 
-    
     // root dir
-    let mut subdir_job = None;
-    let mut push_job = |job| subdir_job = Some(job);
-    
-    let job = Job{path: fs.path().join("dir"), ignore_context: None};
-    let patterns = process_job(job, &mut push_job, ".gitignore");
-    
-    assert_eq!(patterns, Some(vec![fs.path().join("dir/**/foo/").to_str().unwrap().to_owned()]));
-    assert!(subdir_job.is_some());
-    assert!(subdir_job.as_ref().unwrap().path == fs.path().join("dir/bar"));
-    
+    let root = fs.path().join("dir");
+    let job = Job{path: root.clone(), ignore_context: None};
+    let dir = process_job(&OsFileSystem, &root, job, ".gitignore", false);
+
+    assert_eq!(dir.patterns, Some(vec![fs.path().join("dir/**/foo/").into_os_string()]));
+    assert_eq!(dir.child_dirs, [fs.path().join("dir/bar")]);
+    assert!(dir.parent_context.is_none());
+
     // "bar" subdir
-    let mut push_job = |_| panic!("unexpected subdir");
-    let patterns = process_job(subdir_job.unwrap(), &mut push_job, ".gitignore");
+    let job = Job{path: dir.child_dirs[0].clone(), ignore_context: dir.ignore_context.clone()};
+    let sub_dir = process_job(&OsFileSystem, &root, job, ".gitignore", false);
 
-    assert!(patterns.is_none());
+    assert!(sub_dir.patterns.is_none());
+    assert!(sub_dir.child_dirs.is_empty());
+    assert!(Arc::ptr_eq(sub_dir.ignore_context.as_ref().unwrap(), dir.ignore_context.as_ref().unwrap()));
 }
@@ -0,0 +1,329 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use crate::absolute_ignore;
+use crate::file_system::{EntryType, FileSystem};
+use crate::ignore_node::IgnoreNode;
+use crate::job::{self, RootRules};
+use crate::pattern_token::{tokenize_pattern, PatternToken};
+
+
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintKind {
+
+    UnreachableNegation,
+    UnmatchedPattern,
+    DuplicatedPattern,
+    ShadowedPattern,
+    TrailingWhitespace,
+    InvalidDoubleStar,
+    InvalidUtf8,
+    InvalidPattern
+}
+
+impl LintKind {
+
+    fn message(&self) -> &'static str {
+
+        return match self {
+
+            LintKind::UnreachableNegation => "negation never takes effect, a parent directory is excluded",
+            LintKind::UnmatchedPattern => "pattern matches nothing",
+            LintKind::DuplicatedPattern => "pattern duplicated within the file",
+            LintKind::ShadowedPattern => "pattern already defined by an ancestor ignore file",
+            LintKind::TrailingWhitespace => "trailing unescaped whitespace",
+            LintKind::InvalidDoubleStar => "\"**\" not delimited by slashes, it behaves like \"*\"",
+            LintKind::InvalidUtf8 => "invalid UTF-8, decoded lossily",
+            LintKind::InvalidPattern => "invalid glob, the pattern can't be used"
+        };
+    }
+}
+
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+pub struct LintIssue {
+
+    #[serde(serialize_with = "serialize_path_lossy")]
+    pub file: PathBuf,
+    pub line: usize,
+    pub kind: LintKind,
+    pub pattern: String
+}
+
+impl std::fmt::Display for LintIssue {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        return write!(f, "{}:{}: {}: \"{}\"", self.file.display(), self.line, self.kind.message(), self.pattern);
+    }
+}
+
+
+// non-UTF-8 paths can't be represented in JSON strings
+fn serialize_path_lossy<S: serde::Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+
+    return serializer.serialize_str(&path.to_string_lossy());
+}
+
+
+struct LintPattern {
+
+    line_number: usize,
+    line: String,
+    matcher: Gitignore,
+    negated: bool,
+    absolute: bool,
+    matched: bool,
+    matched_reachable: bool
+}
+
+struct LintFile {
+
+    path: PathBuf,
+    patterns: Vec<LintPattern>
+}
+
+
+// the root rules only decide which directories are reachable, they aren't linted themselves
+pub fn lint(fs: &dyn FileSystem, root: &Path, ignore_file_name: &str, root_rules: Option<&RootRules>) -> Vec<LintIssue> {
+
+    struct LintJob {
+
+        path: PathBuf,
+        ignore_context: Option<Arc<IgnoreNode>>,
+        reachable: bool,
+        active_files: Vec<usize>
+    }
+
+    let mut issues = Vec::new();
+    let mut files = Vec::<LintFile>::new();
+    let mut jobs = vec![LintJob{path: root.to_owned(), ignore_context: root_rules.and_then(RootRules::context), reachable: true, active_files: Vec::new()}];
+
+    // unlike "job::process_job", excluded directories are walked as well, so patterns matching only unreachable paths can be found
+    while let Some(LintJob{path, mut ignore_context, reachable, mut active_files}) = jobs.pop() {
+
+        if reachable {
+
+            let ignore_file_path = path.join(ignore_file_name);
+
+            if fs.entry_type(&ignore_file_path) == Some(EntryType::File) {
+
+                let file = parse_lint_file(fs, &ignore_file_path, &path, &files, &active_files, &mut issues);
+
+                // the invalid patterns are left out, instead of failing like the flattening does
+                let lines = file.patterns.iter().map(|pattern| pattern.line.clone()).collect::<Vec<_>>();
+                ignore_context = Some(IgnoreNode::from_lines(&ignore_file_path, &lines, ignore_context));
+
+                active_files.push(files.len());
+                files.push(file);
+            }
+        }
+
+        for (entry_path, entry_type) in job::read_dir(fs, &path) {
+
+            if entry_path.file_name().is_some_and(|name| name == ".git") {
+
+                continue;
+            }
+
+            let is_dir = entry_type == EntryType::Dir;
+
+            for file_idx in &active_files {
+
+                for pattern in files[*file_idx].patterns.iter_mut() {
+
+                    if pattern.matcher.matched(&entry_path, is_dir).is_none() == false {
+
+                        pattern.matched = true;
+                        pattern.matched_reachable |= reachable;
+                    }
+                }
+            }
+
+            if is_dir {
+
+                let entry_reachable = reachable && ignore_context.as_ref().is_some_and(|matcher| matcher.matches(&entry_path)) == false;
+                jobs.push(LintJob{path: entry_path, ignore_context: ignore_context.clone(), reachable: entry_reachable, active_files: active_files.clone()});
+            }
+        }
+    }
+
+    for file in &files {
+
+        for pattern in &file.patterns {
+
+            let kind = match (pattern.matched, pattern.matched_reachable) {
+
+                (false, _) => LintKind::UnmatchedPattern,
+                (true, false) if pattern.negated => LintKind::UnreachableNegation,
+                _ => continue
+            };
+
+            issues.push(LintIssue{file: file.path.clone(), line: pattern.line_number, kind, pattern: pattern.line.clone()});
+        }
+    }
+
+    issues.sort();
+    return issues;
+}
+
+#[test]
+fn test_lint() {
+
+    use crate::file_system::MemoryFileSystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new(".gitignore", b"*.log\nbuild/\n!build/keep.log\nmissing\n*.log\nfoo** \n/src/**/*.tmp\nsrc/\xff\nfoo[\n".to_vec()))
+        .add_dir(Dir::new("build")
+            .add_file(File::new_empty("keep.log")))
+        .add_dir(Dir::new("src")
+            .add_file(File::new_gitignore(&["*.log", "!main.log"]))
+            .add_file(File::new_empty("main.log"))
+            .add_file(File::new_empty("foobar"))
+            .add_dir(Dir::new("sub")
+                .add_file(File::new_empty("a.tmp"))));
+
+    let fs = MemoryFileSystem::from_template(Path::new("/"), &fs_template);
+    let root = Path::new("/dir");
+
+    let issue = |file: &str, line: usize, kind: LintKind, pattern: &str| LintIssue{file: root.join(file), line, kind, pattern: pattern.to_owned()};
+
+    assert_eq!(lint(&fs, root, ".gitignore", None), [
+        issue(".gitignore", 3, LintKind::UnreachableNegation, "!build/keep.log"),
+        issue(".gitignore", 4, LintKind::UnmatchedPattern, "missing"),
+        issue(".gitignore", 5, LintKind::DuplicatedPattern, "*.log"),
+        issue(".gitignore", 6, LintKind::TrailingWhitespace, "foo** "),
+        issue(".gitignore", 6, LintKind::InvalidDoubleStar, "foo** "),
+        issue(".gitignore", 8, LintKind::InvalidUtf8, "src/\u{fffd}"),
+        issue(".gitignore", 9, LintKind::InvalidPattern, "foo["),
+        issue("src/.gitignore", 1, LintKind::ShadowedPattern, "*.log")
+    ]);
+
+    // the ignore files of the directories excluded by the root rules are unreachable
+    let mut rules = RootRules::default();
+    rules.add_excluded_dirs(root, Path::new("/flf.toml"), &["src".to_owned()]);
+
+    assert_eq!(lint(&fs, root, ".gitignore", Some(&rules)).last(), Some(&issue(".gitignore", 9, LintKind::InvalidPattern, "foo[")));
+}
+
+
+fn parse_lint_file(fs: &dyn FileSystem, path: &Path, dir: &Path, files: &[LintFile], ancestor_files: &[usize], issues: &mut Vec<LintIssue>) -> LintFile {
+
+    let mut patterns = Vec::<LintPattern>::new();
+
+    let content = fs.read_file(path).unwrap_or_else(|err| {
+
+        tracing::error!(path = %path.display(), error = %err, "ignore file reading error");
+        std::process::exit(1);
+    });
+
+    for (idx, file_line) in absolute_ignore::decode_ignore_file(&content).iter().enumerate() {
+
+        let line_number = idx + 1;
+        let raw_line = file_line.text.as_str();
+
+        if absolute_ignore::filter_ignore_line(raw_line) == false {
+
+            continue;
+        }
+
+        let line = absolute_ignore::strip_trailing_whitespaces(raw_line);
+        let mut report = |kind| issues.push(LintIssue{file: path.to_owned(), line: line_number, kind, pattern: raw_line.to_owned()});
+
+        if file_line.lossy {
+
+            report(LintKind::InvalidUtf8);
+        }
+
+        if line.len() != raw_line.len() || raw_line.ends_with('\t') {
+
+            report(LintKind::TrailingWhitespace);
+        }
+
+        if has_invalid_double_star(line) {
+
+            report(LintKind::InvalidDoubleStar);
+        }
+
+        if patterns.iter().any(|pattern| pattern.line == line) {
+
+            report(LintKind::DuplicatedPattern);
+        }
+
+        let Some(parsed_pattern) = absolute_ignore::parse_ignore_pattern(line) else {
+
+            report(LintKind::UnmatchedPattern);
+            continue;
+        };
+
+        let shadowed = parsed_pattern.absolute == false && ancestor_files.iter()
+            .flat_map(|file_idx| files[*file_idx].patterns.iter())
+            .any(|pattern| pattern.absolute == false && pattern.line == line);
+
+        if shadowed {
+
+            report(LintKind::ShadowedPattern);
+        }
+
+        // the matcher can't match the raw bytes, see "absolute_ignore::matcher_lines"
+        if file_line.lossy {
+
+            continue;
+        }
+
+        let mut builder = GitignoreBuilder::new(dir);
+        if builder.add_line(Some(path.to_owned()), line).is_err() {
+
+            report(LintKind::InvalidPattern);
+            continue;
+        }
+
+        let matcher = builder.build().expect("matcher build unexpected error");
+
+        patterns.push(LintPattern{
+            line_number,
+            line: line.to_owned(),
+            matcher,
+            negated: parsed_pattern.negated,
+            absolute: parsed_pattern.absolute,
+            matched: false,
+            matched_reachable: false
+        });
+    }
+
+    return LintFile{path: path.to_owned(), patterns};
+}
+
+
+// "**" is only special as a whole path component
+fn has_invalid_double_star(pattern: &str) -> bool {
+
+    return tokenize_pattern(pattern).iter().any(|token| matches!(token, PatternToken::Star(run) if run.len() > 1));
+}
+
+#[test]
+fn test_has_invalid_double_star() {
+
+    #[track_caller]
+    fn assert_invalid(pattern: &str, result: bool) {
+
+        assert_eq!(has_invalid_double_star(pattern), result);
+    }
+
+    assert_invalid("foo", false);
+    assert_invalid("*.log", false);
+    assert_invalid("**/foo", false);
+    assert_invalid("foo/**", false);
+    assert_invalid("foo/**/bar", false);
+    assert_invalid("**", false);
+    assert_invalid("foo**", true);
+    assert_invalid("**foo", true);
+    assert_invalid("foo/**bar", true);
+    assert_invalid("foo/***/bar", false);
+    assert_invalid("\\**", false);
+    assert_invalid("[**]", false);
+}
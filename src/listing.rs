@@ -0,0 +1,214 @@
+use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+use crate::job::{self, Job, RootRules};
+use crate::file_system::{EntryType, FileSystem};
+use crate::ignore_node::IgnoreNode;
+
+
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Selection {
+
+    Included,
+    Ignored
+}
+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ListedEntry {
+
+    pub path: PathBuf,
+    pub is_dir: bool,
+
+    // for ignored directories these account for the whole unreachable subtree
+    pub file_count: u64,
+    pub size: u64
+}
+
+
+// same traversal as "job::process_job", but files are judged by the ignore chain as well
+pub fn list_entries(fs: &dyn FileSystem, root: &Path, ignore_file_name: &str, root_rules: Option<&RootRules>, selection: Selection) -> Vec<ListedEntry> {
+
+    let mut entries = Vec::new();
+    let mut jobs = vec![Job{path: root.to_owned(), ignore_context: root_rules.and_then(RootRules::context)}];
+
+    while let Some(Job{path, ignore_context}) = jobs.pop() {
+
+        let ignore_context = IgnoreNode::from_dir(fs, &path, ignore_file_name, ignore_context);
+
+        for (entry_path, entry_type) in job::read_dir(fs, &path) {
+
+            // the repository itself is neither part of the tree nor ignored, like with "git archive"
+            if entry_path.file_name().is_some_and(|name| name == ".git") {
+
+                continue;
+            }
+
+            let is_dir = entry_type == EntryType::Dir;
+            let ignored = ignore_context.as_ref().is_some_and(|matcher| matcher.matched(&entry_path, is_dir).is_ignore());
+
+            match (selection, ignored, is_dir) {
+
+                (Selection::Included, false, true) => {
+
+                    entries.push(ListedEntry{path: entry_path.clone(), is_dir, file_count: 0, size: 0});
+                    jobs.push(Job{path: entry_path, ignore_context: ignore_context.clone()});
+                },
+                (Selection::Included, false, false) | (Selection::Ignored, true, false) => {
+
+                    let size = fs.file_size(&entry_path).unwrap_or(0);
+                    entries.push(ListedEntry{path: entry_path, is_dir, file_count: 1, size});
+                },
+                (Selection::Ignored, true, true) => {
+
+                    let (file_count, size) = measure_dir(fs, &entry_path);
+                    entries.push(ListedEntry{path: entry_path, is_dir, file_count, size});
+                },
+                (Selection::Ignored, false, true) => {
+
+                    jobs.push(Job{path: entry_path, ignore_context: ignore_context.clone()});
+                },
+                (Selection::Included, true, _) | (Selection::Ignored, false, false) => {}
+            }
+        }
+    }
+
+    entries.sort_by(|e1, e2| e1.path.cmp(&e2.path));
+    return entries;
+}
+
+#[test]
+fn test_list_entries() {
+
+    use crate::file_system::MemoryFileSystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["*.log", "build/"]))
+        .add_file(File::new("main.log", b"12345".to_vec()))
+        .add_dir(Dir::new("build")
+            .add_file(File::new("a.o", b"123".to_vec()))
+            .add_dir(Dir::new("obj")
+                .add_file(File::new("b.o", b"1".to_vec()))))
+        .add_dir(Dir::new("src")
+            .add_file(File::new_gitignore(&["!keep.log"]))
+            .add_file(File::new("keep.log", b"12".to_vec()))
+            .add_file(File::new_empty("debug.log"))
+            .add_file(File::new("main.rs", b"1234".to_vec())))
+        .add_dir(Dir::new(".git")
+            .add_file(File::new("HEAD", b"ref: refs/heads/main".to_vec())));
+
+    let fs = MemoryFileSystem::from_template(Path::new("/"), &fs_template);
+    let root = Path::new("/dir");
+
+    let list = |root_rules: Option<&RootRules>, selection| list_entries(&fs, root, ".gitignore", root_rules, selection).into_iter()
+        .map(|entry| (entry.path.strip_prefix(root).unwrap().to_str().unwrap().to_owned(), entry.file_count, entry.size))
+        .collect::<Vec<_>>();
+
+    let entry = |path: &str, file_count: u64, size: u64| (path.to_owned(), file_count, size);
+
+    assert_eq!(list(None, Selection::Included), [
+        entry(".gitignore", 1, 12),
+        entry("src", 0, 0),
+        entry("src/.gitignore", 1, 9),
+        entry("src/keep.log", 1, 2),
+        entry("src/main.rs", 1, 4)
+    ]);
+
+    assert_eq!(list(None, Selection::Ignored), [
+        entry("build", 2, 4),
+        entry("main.log", 1, 5),
+        entry("src/debug.log", 1, 0)
+    ]);
+
+    // the ignore files of the tree take precedence over the root rules
+    let mut rules = RootRules::default();
+    rules.add_patterns(root, Path::new("/flf.toml"), &["*.rs".to_owned(), "!main.log".to_owned()], false);
+
+    assert_eq!(list(Some(&rules), Selection::Ignored), [
+        entry("build", 2, 4),
+        entry("main.log", 1, 5),
+        entry("src/debug.log", 1, 0),
+        entry("src/main.rs", 1, 4)
+    ]);
+}
+
+
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy)]
+pub struct Summary {
+
+    pub file_count: u64,
+    pub size: u64
+}
+
+// groups the entries by their top-level directory, entries directly inside the root are grouped under "."
+pub fn summarize(root: &Path, entries: &[ListedEntry]) -> BTreeMap<PathBuf, Summary> {
+
+    let mut summaries = BTreeMap::<PathBuf, Summary>::new();
+
+    for entry in entries {
+
+        let relative_path = entry.path.strip_prefix(root).expect("entry outside of the root");
+        let mut components = relative_path.components();
+
+        let first_component = components.next().expect("empty entry path");
+        let group = if entry.is_dir || components.next().is_some() { PathBuf::from(first_component.as_os_str()) } else { PathBuf::from(".") };
+
+        let summary = summaries.entry(group).or_default();
+        summary.file_count += entry.file_count;
+        summary.size += entry.size;
+    }
+
+    return summaries;
+}
+
+#[test]
+fn test_summarize() {
+
+    let root = Path::new("root");
+    let entry = |path: &str, is_dir: bool, file_count: u64, size: u64| ListedEntry{path: root.join(path), is_dir, file_count, size};
+
+    let entries = [
+        entry("a", false, 1, 10),
+        entry("b", false, 1, 5),
+        entry("foo", true, 0, 0),
+        entry("foo/c", false, 1, 7),
+        entry("foo/bar/d", false, 1, 1),
+        entry("ignored_dir", true, 3, 30)
+    ];
+
+    let expected = BTreeMap::from([
+        (PathBuf::from("."), Summary{file_count: 2, size: 15}),
+        (PathBuf::from("foo"), Summary{file_count: 2, size: 8}),
+        (PathBuf::from("ignored_dir"), Summary{file_count: 3, size: 30})
+    ]);
+
+    assert_eq!(summarize(root, &entries), expected);
+}
+
+
+// counts every file inside the directory, without applying any ignore rule
+fn measure_dir(fs: &dyn FileSystem, path: &Path) -> (u64, u64) {
+
+    let mut file_count = 0;
+    let mut size = 0;
+    let mut dirs = vec![path.to_owned()];
+
+    while let Some(dir) = dirs.pop() {
+
+        for (entry_path, entry_type) in job::read_dir(fs, &dir) {
+
+            if entry_type == EntryType::Dir {
+
+                dirs.push(entry_path);
+                continue;
+            }
+
+            file_count += 1;
+            size += fs.file_size(&entry_path).unwrap_or(0);
+        }
+    }
+
+    return (file_count, size);
+}
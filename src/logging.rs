@@ -0,0 +1,122 @@
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::fmt::MakeWriter;
+
+
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LogFormat {
+
+    Text,
+
+    // one JSON object per event
+    Json
+}
+
+
+// warnings are shown by default, "-v" adds the loaded ignore files and pruned directories, "-vv" every visited directory
+pub fn level_filter(verbosity: u8, quiet: bool) -> LevelFilter {
+
+    if quiet {
+
+        return LevelFilter::ERROR;
+    }
+
+    return match verbosity {
+
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::INFO,
+        2 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE
+    };
+}
+
+fn build_subscriber<W>(level: LevelFilter, format: LogFormat, writer: W) -> Box<dyn tracing::Subscriber + Send + Sync>
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static
+{
+
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(writer)
+        .with_target(false);
+
+    return match format {
+
+        LogFormat::Text => {
+
+            use std::io::IsTerminal;
+
+            Box::new(builder.without_time().with_ansi(std::io::stderr().is_terminal()).finish())
+        },
+        LogFormat::Json => Box::new(builder.json().flatten_event(true).finish())
+    };
+}
+
+// the events are written to stderr, stdout is left to the output
+pub fn init(level: LevelFilter, format: LogFormat) {
+
+    tracing::subscriber::set_global_default(build_subscriber(level, format, std::io::stderr)).expect("logging initialization error");
+}
+
+#[test]
+fn test_logging_events() {
+
+    use std::sync::{Arc, Mutex};
+    use crate::file_system::MemoryFileSystem;
+    use crate::filesystem::template::{Dir, File};
+    use crate::job::{self, FlattenOptions};
+
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuffer {
+
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+
+            self.0.lock().unwrap().extend_from_slice(buf);
+            return Ok(buf.len());
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+
+            return Ok(());
+        }
+    }
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["build/"]))
+        .add_dir(Dir::new("build"))
+        .add_dir(Dir::new("src"));
+
+    let fs = MemoryFileSystem::from_template(std::path::Path::new("/"), &fs_template);
+
+    let events = |verbosity: u8| {
+
+        let buffer = SharedBuffer::default();
+        let writer = buffer.clone();
+        let subscriber = build_subscriber(level_filter(verbosity, false), LogFormat::Json, move || writer.clone());
+
+        tracing::subscriber::with_default(subscriber, || job::flatten(&fs, std::path::Path::new("/dir"), ".gitignore", FlattenOptions::default()));
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+
+        return output.lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).expect("invalid JSON event"))
+            .collect::<Vec<_>>();
+    };
+
+    assert!(events(0).is_empty());
+
+    let info_events = events(1);
+    let messages = info_events.iter().map(|event| event["message"].as_str().unwrap()).collect::<Vec<_>>();
+    assert_eq!(messages, ["ignore file loaded", "directory pruned"]);
+
+    assert_eq!(info_events[0]["path"], "/dir/.gitignore");
+    assert_eq!(info_events[1]["path"], "/dir/build");
+    assert_eq!(info_events[1]["pattern"], "build/");
+    assert_eq!(info_events[1]["source"], "/dir/.gitignore");
+
+    // every visited directory
+    assert_eq!(events(2).iter().filter(|event| event["message"] == "directory visited").count(), 2);
+}
@@ -1,39 +1,423 @@
 #![allow(clippy::needless_return)]
 #![allow(clippy::bool_comparison)]
+#![allow(clippy::bool_assert_comparison)]
 
 mod cli;
+mod config;
+// test fixtures, not every helper is used
+#[allow(dead_code, clippy::redundant_static_lifetimes, clippy::useless_conversion)]
 mod filesystem;
+mod file_system;
 mod ignore_node;
+// not used by the traversal yet
+#[allow(dead_code, clippy::identity_op, clippy::manual_is_multiple_of)]
 mod batch;
 mod absolute_ignore;
+mod archive;
+mod cache;
+mod pattern_token;
 mod job;
+mod explain;
+mod generate;
+mod diff;
+mod files_from;
+mod listing;
+mod lint;
+mod managed_block;
+mod output;
+mod logging;
+mod stats;
+mod pack;
+mod git_compat;
+mod git_index;
+mod git_tree;
+mod watch;
 
 
 
 fn main() {
 
     let matches = cli::build_cli_parser().get_matches();
-    let args = cli::parse_cli_matches(&matches);
 
-    let mut patterns = Vec::new();
-    let mut jobs = vec![job::Job{path: args.path, ignore_context: None}];
+    let log_args = cli::parse_log_matches(&matches);
+    logging::init(log_args.level, log_args.format);
 
-    while jobs.is_empty() == false {
+    let config = match cli::parse_start_path(&matches) {
 
-        
-        let job = jobs.pop().expect("invalid job stack size");
-        let push_job = &mut |job| jobs.push(job);
-        
-        let new_patterns = job::process_job(job, push_job, &args.ignore_file_name);
-        
-        if let Some(new_patterns) = new_patterns {
+        Some(start_path) => config::find_config(&start_path).unwrap_or_else(|err| {
 
-            patterns.extend(new_patterns);
+            tracing::error!(error = %err, "configuration reading failed");
+            std::process::exit(1);
+        }),
+        None => config::Config::default()
+    };
+
+    match matches.subcommand() {
+
+        Some(("explain", explain_matches)) => return run_explain(cli::parse_explain_matches(explain_matches, &config)),
+        Some(("ls", ls_matches)) => return run_ls(cli::parse_ls_matches(ls_matches, &config)),
+        Some(("lint", lint_matches)) => return run_lint(cli::parse_lint_matches(lint_matches, &config)),
+        Some(("watch", watch_matches)) => return run_watch(cli::parse_watch_matches(watch_matches, &config)),
+        Some(("pack", pack_matches)) => return run_pack(cli::parse_pack_matches(pack_matches, &config)),
+        Some(("copy", copy_matches)) => return run_copy(cli::parse_copy_matches(copy_matches, &config)),
+        Some(("diff", diff_matches)) => return run_diff(cli::parse_diff_matches(diff_matches, &config)),
+        Some(("completions", completions_matches)) => return run_completions(cli::parse_completions_matches(completions_matches)),
+        Some(("man", man_matches)) => return run_man(cli::parse_man_matches(man_matches)),
+        _ => {}
+    }
+
+    let args = cli::parse_cli_matches(&matches, &config).unwrap_or_else(|err| {
+
+        tracing::error!(error = %err, "search path resolution failed");
+        std::process::exit(1);
+    });
+
+    let archive_format = archive::ArchiveFormat::from_path(&args.path).filter(|_| args.path.is_file());
+
+    if archive_format.is_some() && (args.cache.is_some() || args.files_from.is_some() || args.tracked_only || args.rev.is_some()) {
+
+        tracing::error!("an archive path can't be combined with --cache, --files-from, --tracked-only or --rev");
+        std::process::exit(1);
+    }
+
+    let mut cache = args.cache.as_ref().map(|cache_path| cache::FlattenCache::load(cache_path, &args.path, &args.ignore_file_name, args.keep_comments));
+
+    let mut stats = args.stats.map(|_| stats::FlattenStats::default());
+
+    let patterns = match args.files_from {
+
+        Some(ref list_path) => {
+
+            let files = files_from::read_file_list(list_path).unwrap_or_else(|err| {
+
+                tracing::error!(path = %list_path.display(), error = %err, "ignore file list reading failed");
+                std::process::exit(1);
+            });
+
+            files_from::flatten_files(&args.path, &files, args.keep_comments, args.order, Some(&build_root_rules(&args.rules, &args.path, args.keep_comments)), stats.as_mut())
+        },
+        None if archive_format.is_some() => {
+
+            let format = archive_format.expect("missing archive format");
+
+            // as if the archive were unpacked in the working directory
+            let root = std::path::Path::new(".");
+
+            let fs = archive::load_archive(&args.path, format, root, &args.ignore_file_name).unwrap_or_else(|err| {
+
+                tracing::error!(path = %args.path.display(), error = %err, "archive reading failed");
+                std::process::exit(1);
+            });
+
+            job::flatten(&fs, root, &args.ignore_file_name, job::FlattenOptions{keep_comments: args.keep_comments, order: args.order, root_rules: Some(&build_root_rules(&args.rules, root, args.keep_comments)), stats: stats.as_mut(), ..Default::default()})
+        },
+        None if args.tracked_only => {
+
+            let files = git_index::tracked_ignore_files(&args.path, &args.ignore_file_name).unwrap_or_else(|err| {
+
+                tracing::error!(error = %err, "tracked ignore files listing failed");
+                std::process::exit(1);
+            });
+
+            files_from::flatten_files(&args.path, &files, args.keep_comments, args.order, Some(&build_root_rules(&args.rules, &args.path, args.keep_comments)), stats.as_mut())
+        },
+        None => {
+
+            let fs: Box<dyn file_system::FileSystem> = match args.rev {
+
+                Some(ref rev) => Box::new(git_tree::GitTreeFileSystem::new(&args.path, rev).unwrap_or_else(|err| {
+
+                    tracing::error!(revision = rev, error = %err, "git tree reading failed");
+                    std::process::exit(1);
+                })),
+                None => Box::new(file_system::OsFileSystem)
+            };
+
+            job::flatten(fs.as_ref(), &args.path, &args.ignore_file_name, job::FlattenOptions{keep_comments: args.keep_comments, order: args.order, root_rules: Some(&build_root_rules(&args.rules, &args.path, args.keep_comments)), cache: cache.as_mut(), stats: stats.as_mut()})
+        }
+    };
+
+    if let (Some(stats), Some(format)) = (stats, args.stats) {
+
+        match format {
+
+            stats::StatsFormat::Text => eprintln!("{}", stats),
+            stats::StatsFormat::Json => eprintln!("{}", stats.to_json())
+        }
+    }
+
+    if let (Some(cache), Some(cache_path)) = (cache, &args.cache) {
+
+        // the output is still valid without the cache
+        if let Err(err) = cache.save(cache_path) {
+
+            tracing::warn!(path = %cache_path.display(), error = %err, "cache writing failed");
+        }
+    }
+
+    let (lines, skipped_count) = output::encode_lines(&patterns, args.non_utf8_mode);
+
+    if skipped_count > 0 {
+
+        tracing::warn!(count = skipped_count, "skipped non-UTF-8 patterns");
+    }
+
+    if let Some(ref block_file) = args.update_block {
+
+        if let Err(err) = managed_block::update_block_in_file(block_file, &lines) {
+
+            tracing::error!(path = %block_file.display(), error = %err, "managed block update failed");
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    write_lines(&lines);
+}
+
+// configured and command line patterns, applied under the root of every traversed tree with the command line taking precedence
+fn build_root_rules(args: &cli::RuleArguments, root: &std::path::Path, keep_comments: bool) -> job::RootRules {
+
+    let mut rules = job::RootRules::default();
+
+    if let Some(ref config_path) = args.config_path {
+
+        rules.add_patterns(root, config_path, &args.global_patterns, keep_comments);
+        rules.add_excluded_dirs(root, config_path, &args.excluded_dirs);
+    }
+
+    for extra_file in &args.extra_files {
+
+        // read from the disk, even when the tree comes from an archive or a revision
+        rules.add_patterns_from_file(&file_system::OsFileSystem, root, extra_file, keep_comments).unwrap_or_else(|err| {
+
+            tracing::error!(path = %extra_file.display(), error = %err, "extra ignore file reading failed");
+            std::process::exit(1);
+        });
+    }
+
+    rules.add_patterns(root, std::path::Path::new("command line"), &args.extra_patterns, keep_comments);
+
+    return rules;
+}
+
+fn write_lines(lines: &[Vec<u8>]) {
+
+    use std::io::Write;
+
+    let mut stdout = std::io::stdout().lock();
+
+    for line in lines {
+
+        stdout.write_all(line).and_then(|_| stdout.write_all(b"\n")).expect("stdout write error");
+    }
+}
+
+
+fn run_explain(args: cli::ExplainArguments) {
+
+    let root_rules = build_root_rules(&args.rules, &args.root, false);
+    let mut any_ignored = false;
+
+    for path in args.paths {
+
+        match explain::explain_path(&file_system::OsFileSystem, &args.root, &path, &args.ignore_file_name, Some(&root_rules)) {
+
+            Ok(explanation) => {
+
+                any_ignored |= explanation.is_ignored();
+                println!("{}", explain::format_explanation(&path, &explanation));
+            },
+            Err(err) => {
+
+                tracing::error!(path = %path.display(), error = %err, "path explanation failed");
+                std::process::exit(1);
+            }
         }
     }
 
-    for pattern in patterns {
+    // same convention as "git check-ignore"
+    if any_ignored == false {
+
+        std::process::exit(1);
+    }
+}
+
+
+fn run_ls(args: cli::ListArguments) {
+
+    let entries = listing::list_entries(&file_system::OsFileSystem, &args.path, &args.ignore_file_name, Some(&build_root_rules(&args.rules, &args.path, false)), args.selection);
+
+    if (args.count || args.du) == false {
+
+        for entry in entries {
+
+            let suffix = if entry.is_dir { "/" } else { "" };
+            println!("{}{}", entry.path.display(), suffix);
+        }
 
-        println!("{}", pattern);
+        return;
     }
+
+    for (group, summary) in listing::summarize(&args.path, &entries) {
+
+        let mut columns = Vec::new();
+
+        if args.count {
+
+            columns.push(summary.file_count.to_string());
+        }
+
+        if args.du {
+
+            columns.push(summary.size.to_string());
+        }
+
+        columns.push(group.display().to_string());
+        println!("{}", columns.join("\t"));
+    }
+}
+
+
+fn run_lint(args: cli::LintArguments) {
+
+    let issues = lint::lint(&file_system::OsFileSystem, &args.path, &args.ignore_file_name, Some(&build_root_rules(&args.rules, &args.path, false)));
+
+    for issue in &issues {
+
+        if args.json {
+
+            println!("{}", serde_json::to_string(issue).expect("lint issue serialization error"));
+        }
+        else {
+
+            println!("{}", issue);
+        }
+    }
+
+    if issues.is_empty() == false {
+
+        std::process::exit(1);
+    }
+}
+
+
+fn run_pack(args: cli::PackArguments) {
+
+    if let Err(err) = pack::pack_tree(&args.path, &args.ignore_file_name, Some(&build_root_rules(&args.rules, &args.path, false)), &args.output) {
+
+        tracing::error!(path = %args.output.display(), error = %err, "archive writing failed");
+        std::process::exit(1);
+    }
+}
+
+
+fn run_copy(args: cli::CopyArguments) {
+
+    if let Err(err) = pack::copy_tree(&args.path, &args.ignore_file_name, Some(&build_root_rules(&args.rules, &args.path, false)), &args.dest) {
+
+        tracing::error!(destination = %args.dest.display(), error = %err, "tree copy failed");
+        std::process::exit(1);
+    }
+}
+
+
+fn run_diff(args: cli::DiffArguments) {
+
+    for root in [&args.old_root, &args.new_root] {
+
+        if root.is_dir() == false {
+
+            tracing::error!(path = %root.display(), "the compared roots must be directories");
+            std::process::exit(2);
+        }
+    }
+
+    let fs = file_system::OsFileSystem;
+    let old_rules = build_root_rules(&args.rules, &args.old_root, false);
+    let new_rules = build_root_rules(&args.rules, &args.new_root, false);
+
+    let changes = diff::diff_trees(&fs, &args.old_root, Some(&old_rules), &fs, &args.new_root, Some(&new_rules), &args.ignore_file_name);
+
+    for line in diff::format_changes(&changes) {
+
+        println!("{}", line);
+    }
+
+    // same convention as "diff"
+    if changes.is_empty() == false {
+
+        std::process::exit(1);
+    }
+}
+
+
+fn run_completions(args: cli::CompletionsArguments) {
+
+    generate::write_completions(args.shell, &mut std::io::stdout().lock());
+}
+
+
+fn run_man(args: cli::ManArguments) {
+
+    let result = match args.out_dir {
+
+        Some(ref out_dir) => generate::write_man_pages(out_dir),
+        None => generate::write_man_page(&mut std::io::stdout().lock())
+    };
+
+    if let Err(err) = result {
+
+        tracing::error!(error = %err, "man page writing failed");
+        std::process::exit(1);
+    }
+}
+
+
+#[cfg(target_os = "linux")]
+fn run_watch(args: cli::WatchArguments) {
+
+    fn watch_error_handler(err: impl std::fmt::Display) -> ! {
+
+        tracing::error!(error = %err, "watch failed");
+        std::process::exit(1);
+    }
+
+    let write_output = |patterns: &[std::ffi::OsString]| {
+
+        let (lines, _) = output::encode_lines(patterns, output::NonUtf8Mode::Raw);
+        let content = lines.iter().flat_map(|line| line.iter().copied().chain([b'\n'])).collect::<Vec<_>>();
+
+        output::write_file_atomically(&args.output, &content).unwrap_or_else(|err| watch_error_handler(err));
+    };
+
+    let mut watcher = watch::Watcher::new(&args.path, &args.ignore_file_name, args.keep_comments, Some(&build_root_rules(&args.rules, &args.path, args.keep_comments))).unwrap_or_else(|err| watch_error_handler(err));
+
+    let mut patterns = watcher.tree().patterns(args.order);
+    write_output(&patterns);
+
+    loop {
+
+        if watcher.wait_for_changes().unwrap_or_else(|err| watch_error_handler(err)) == false {
+
+            continue;
+        }
+
+        let new_patterns = watcher.tree().patterns(args.order);
+
+        // avoid touching the file when nothing changed
+        if new_patterns != patterns {
+
+            patterns = new_patterns;
+            write_output(&patterns);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn run_watch(_args: cli::WatchArguments) {
+
+    tracing::error!("watch mode relies on inotify, it's only available on Linux");
+    std::process::exit(1);
 }
@@ -0,0 +1,186 @@
+pub const BLOCK_BEGIN_MARKER: &str = "# BEGIN flf";
+pub const BLOCK_END_MARKER: &str = "# END flf";
+
+
+
+// replaces the lines between the block markers, everything outside the block is kept byte-for-byte
+pub fn update_block(content: &[u8], block_lines: &[impl AsRef<[u8]>]) -> anyhow::Result<Vec<u8>> {
+
+    let line_ending = detect_line_ending(content);
+
+    let mut block = Vec::new();
+    for line in block_lines {
+
+        block.extend_from_slice(line.as_ref());
+        block.extend_from_slice(line_ending);
+    }
+
+    let mut result = Vec::with_capacity(content.len() + block.len());
+
+    match find_block(content)? {
+
+        Some((block_start, block_end)) => {
+
+            result.extend_from_slice(&content[..block_start]);
+            result.extend_from_slice(&block);
+            result.extend_from_slice(&content[block_end..]);
+        },
+        None => {
+
+            result.extend_from_slice(content);
+
+            if content.is_empty() == false && content.ends_with(b"\n") == false {
+
+                result.extend_from_slice(line_ending);
+            }
+
+            result.extend_from_slice(BLOCK_BEGIN_MARKER.as_bytes());
+            result.extend_from_slice(line_ending);
+            result.extend_from_slice(&block);
+            result.extend_from_slice(BLOCK_END_MARKER.as_bytes());
+            result.extend_from_slice(line_ending);
+        }
+    }
+
+    return Ok(result);
+}
+
+pub fn update_block_in_file(path: &std::path::Path, block_lines: &[impl AsRef<[u8]>]) -> anyhow::Result<()> {
+
+    let content = match std::fs::read(path) {
+
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err.into())
+    };
+
+    let new_content = update_block(&content, block_lines)?;
+
+    // avoid touching the file when nothing changed
+    if new_content != content {
+
+        std::fs::write(path, new_content)?;
+    }
+
+    return Ok(());
+}
+
+#[test]
+fn test_update_block() {
+
+    #[track_caller]
+    fn assert_update(content: &str, lines: &[&str], expected: &str) {
+
+        let lines = lines.iter().map(|line| line.to_string()).collect::<Vec<_>>();
+        let result = update_block(content.as_bytes(), &lines).expect("unexpected block update error");
+
+        assert_eq!(String::from_utf8(result.clone()).unwrap(), expected);
+
+        // a second update must not change anything
+        assert_eq!(update_block(&result, &lines).expect("unexpected block update error"), result);
+    }
+
+    // missing markers
+    assert_update("", &["foo"], "# BEGIN flf\nfoo\n# END flf\n");
+    assert_update("bar\n", &["foo"], "bar\n# BEGIN flf\nfoo\n# END flf\n");
+    assert_update("bar", &["foo"], "bar\n# BEGIN flf\nfoo\n# END flf\n");
+    assert_update("bar\r\n", &["foo"], "bar\r\n# BEGIN flf\r\nfoo\r\n# END flf\r\n");
+
+    // existing markers
+    assert_update("a\n# BEGIN flf\nold\n# END flf\nb\n", &["foo", "bar"], "a\n# BEGIN flf\nfoo\nbar\n# END flf\nb\n");
+    assert_update("a\r\n# BEGIN flf\r\nold\r\n# END flf\r\nb", &["foo"], "a\r\n# BEGIN flf\r\nfoo\r\n# END flf\r\nb");
+    assert_update("# BEGIN flf\n# END flf", &["foo"], "# BEGIN flf\nfoo\n# END flf");
+    assert_update("# BEGIN flf\nold\n# END flf\n", &[], "# BEGIN flf\n# END flf\n");
+    assert_update("  foo  \n# BEGIN flf\n# END flf\n\n\n", &["foo"], "  foo  \n# BEGIN flf\nfoo\n# END flf\n\n\n");
+}
+
+#[test]
+fn test_update_block_invalid_markers() {
+
+    let no_lines: &[&str] = &[];
+
+    assert!(update_block(b"# BEGIN flf\nfoo\n", no_lines).is_err());
+    assert!(update_block(b"foo\n# END flf\n", no_lines).is_err());
+    assert!(update_block(b"# END flf\n# BEGIN flf\n", no_lines).is_err());
+}
+
+#[test]
+fn test_update_block_in_file() {
+
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new("root_ignore", b"manual\r\n".to_vec()));
+
+    let fs = TmpFilesystem::new(&fs_template);
+    let lines = vec!["dir/**/foo".to_owned()];
+
+    // existing file
+    let path = fs.path().join("dir/root_ignore");
+    update_block_in_file(&path, &lines).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"manual\r\n# BEGIN flf\r\ndir/**/foo\r\n# END flf\r\n");
+
+    // missing file
+    let path = fs.path().join("dir/new_ignore");
+    update_block_in_file(&path, &lines).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"# BEGIN flf\ndir/**/foo\n# END flf\n");
+}
+
+
+// returns the byte range between the end of the begin marker line and the start of the end marker line
+fn find_block(content: &[u8]) -> anyhow::Result<Option<(usize, usize)>> {
+
+    let mut block_start = None;
+    let mut offset = 0;
+
+    for line in content.split_inclusive(|byte| *byte == b'\n') {
+
+        let line_start = offset;
+        offset += line.len();
+
+        let line = strip_line_ending(line);
+
+        if line == BLOCK_BEGIN_MARKER.as_bytes() {
+
+            if block_start.is_some() {
+
+                anyhow::bail!("nested \"{}\" marker", BLOCK_BEGIN_MARKER);
+            }
+
+            block_start = Some(offset);
+        }
+        else if line == BLOCK_END_MARKER.as_bytes() {
+
+            match block_start {
+
+                Some(block_start) => return Ok(Some((block_start, line_start))),
+                None => anyhow::bail!("\"{}\" marker without a preceding \"{}\" marker", BLOCK_END_MARKER, BLOCK_BEGIN_MARKER)
+            }
+        }
+    }
+
+    if block_start.is_some() {
+
+        anyhow::bail!("\"{}\" marker without a matching \"{}\" marker", BLOCK_BEGIN_MARKER, BLOCK_END_MARKER);
+    }
+
+    return Ok(None);
+}
+
+fn strip_line_ending(line: &[u8]) -> &[u8] {
+
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    return line.strip_suffix(b"\r").unwrap_or(line);
+}
+
+// uses the line ending of the first line, defaulting to LF
+fn detect_line_ending(content: &[u8]) -> &'static [u8] {
+
+    match content.iter().position(|byte| *byte == b'\n') {
+
+        Some(idx) if idx > 0 && content[idx - 1] == b'\r' => b"\r\n",
+        _ => b"\n"
+    }
+}
@@ -0,0 +1,122 @@
+use std::ffi::{OsStr, OsString};
+
+
+
+// how lines that aren't valid UTF-8 are written
+#[derive(Debug, PartialEq, Eq, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NonUtf8Mode {
+
+    // written as raw bytes, lossless on Unix
+    Raw,
+    Skip,
+
+    // invalid bytes are written as "\xNN", for display only since gitignore has no such escape
+    Escape
+}
+
+
+pub fn encode_line(line: &OsStr, mode: NonUtf8Mode) -> Option<Vec<u8>> {
+
+    if let Some(line) = line.to_str() {
+
+        return Some(line.as_bytes().to_owned());
+    }
+
+    let bytes = line.as_encoded_bytes();
+
+    return match mode {
+
+        NonUtf8Mode::Raw => Some(bytes.to_owned()),
+        NonUtf8Mode::Skip => None,
+        NonUtf8Mode::Escape => {
+
+            let mut result = String::with_capacity(bytes.len());
+
+            for chunk in bytes.utf8_chunks() {
+
+                result.push_str(chunk.valid());
+                chunk.invalid().iter().for_each(|byte| result.push_str(&format!("\\x{:02x}", byte)));
+            }
+
+            Some(result.into_bytes())
+        }
+    };
+}
+
+#[cfg(unix)]
+#[test]
+fn test_encode_line() {
+
+    use std::os::unix::ffi::OsStrExt;
+
+    #[track_caller]
+    fn assert_encode(line: &[u8], mode: NonUtf8Mode, expected: Option<&[u8]>) {
+
+        assert_eq!(encode_line(OsStr::from_bytes(line), mode).as_deref(), expected);
+    }
+
+    for mode in [NonUtf8Mode::Raw, NonUtf8Mode::Skip, NonUtf8Mode::Escape] {
+
+        assert_encode(b"dir/**/foo", mode, Some(b"dir/**/foo"));
+        assert_encode("dir/\u{e9}/foo".as_bytes(), mode, Some("dir/\u{e9}/foo".as_bytes()));
+    }
+
+    assert_encode(b"dir_\xff/foo", NonUtf8Mode::Raw, Some(b"dir_\xff/foo"));
+    assert_encode(b"dir_\xff/foo", NonUtf8Mode::Skip, None);
+    assert_encode(b"dir_\xff\xfe/\xc3\xa9", NonUtf8Mode::Escape, Some("dir_\\xff\\xfe/\u{e9}".as_bytes()));
+}
+
+
+// returns the encoded lines and the number of skipped ones
+pub fn encode_lines(lines: &[OsString], mode: NonUtf8Mode) -> (Vec<Vec<u8>>, usize) {
+
+    let encoded_lines = lines.iter().filter_map(|line| encode_line(line, mode)).collect::<Vec<_>>();
+    let skipped_count = lines.len() - encoded_lines.len();
+
+    return (encoded_lines, skipped_count);
+}
+
+
+// readers of the file see either the old or the new content, never a partial write
+pub fn write_file_atomically(path: &std::path::Path, content: &[u8]) -> std::io::Result<()> {
+
+    let file_name = path.file_name().ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "missing file name"))?;
+
+    // same directory, since renames across filesystems aren't atomic
+    let mut tmp_name = OsString::from(".");
+    tmp_name.push(file_name);
+    tmp_name.push(".flf-tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    std::fs::write(&tmp_path, content)?;
+
+    return std::fs::rename(&tmp_path, path).inspect_err(|_| { let _ = std::fs::remove_file(&tmp_path); });
+}
+
+#[test]
+fn test_write_file_atomically() {
+
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new("out", b"old\n".to_vec()));
+
+    let fs = TmpFilesystem::new(&fs_template);
+    let dir = fs.path().join("dir");
+
+    // existing file
+    write_file_atomically(&dir.join("out"), b"new\n").unwrap();
+    assert_eq!(std::fs::read(dir.join("out")).unwrap(), b"new\n");
+
+    // missing file
+    write_file_atomically(&dir.join("created"), b"foo\n").unwrap();
+    assert_eq!(std::fs::read(dir.join("created")).unwrap(), b"foo\n");
+
+    // no leftover temporary file
+    let mut names = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["created", "out"]);
+}
@@ -0,0 +1,290 @@
+use std::io::Write;
+use std::path::Path;
+use crate::archive::ArchiveFormat;
+use crate::file_system::OsFileSystem;
+use crate::job::RootRules;
+use crate::listing::{self, ListedEntry, Selection};
+
+
+
+// included entries of the tree, minus the given output path so a previous run's archive isn't packed into the new one
+// the modes and symlinks are copied from the disk, so the tree is listed from it as well
+fn included_entries(root: &Path, ignore_file_name: &str, root_rules: Option<&RootRules>, output: &Path) -> Vec<ListedEntry> {
+
+    let output = output.canonicalize().ok();
+
+    return listing::list_entries(&OsFileSystem, root, ignore_file_name, root_rules, Selection::Included).into_iter()
+        .filter(|entry| output.is_none() || entry.path.canonicalize().ok() != output)
+        .collect();
+}
+
+fn relative_path<'a>(root: &Path, entry: &'a ListedEntry) -> &'a Path {
+
+    return entry.path.strip_prefix(root).expect("entry outside of the root");
+}
+
+
+// copies the included files under the destination, keeping their modes and symlinks
+pub fn copy_tree(root: &Path, ignore_file_name: &str, root_rules: Option<&RootRules>, dest: &Path) -> anyhow::Result<()> {
+
+    if dest.exists() && std::fs::read_dir(dest)?.next().is_some() {
+
+        anyhow::bail!("the destination isn't empty, path: {}", dest.display());
+    }
+
+    let entries = included_entries(root, ignore_file_name, root_rules, dest);
+    let mut dirs = Vec::new();
+
+    std::fs::create_dir_all(dest)?;
+
+    // parents are listed before their entries
+    for entry in &entries {
+
+        let target = dest.join(relative_path(root, entry));
+        let metadata = std::fs::symlink_metadata(&entry.path)?;
+
+        if metadata.is_dir() {
+
+            std::fs::create_dir(&target)?;
+            dirs.push((target, metadata.permissions()));
+        }
+        else if metadata.is_symlink() {
+
+            create_symlink(&std::fs::read_link(&entry.path)?, &target)?;
+        }
+        else {
+
+            std::fs::copy(&entry.path, &target)?;
+        }
+    }
+
+    // read-only directories are applied last, once their content is written
+    for (dir, permissions) in dirs.into_iter().rev() {
+
+        std::fs::set_permissions(dir, permissions)?;
+    }
+
+    return Ok(());
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, path: &Path) -> std::io::Result<()> {
+
+    return std::os::unix::fs::symlink(target, path);
+}
+
+#[cfg(not(unix))]
+fn create_symlink(_target: &Path, path: &Path) -> std::io::Result<()> {
+
+    return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, format!("symlinks can't be copied on this platform, path: {}", path.display())));
+}
+
+#[test]
+fn test_copy_tree() {
+
+    use crate::filesystem::checker::Checker;
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["*.log", "build/"]))
+        .add_file(File::new("main.log", b"log".to_vec()))
+        .add_file(File::new("run.sh", b"#!/bin/sh".to_vec()))
+        .add_dir(Dir::new("build")
+            .add_file(File::new_empty("a.o")))
+        .add_dir(Dir::new("src")
+            .add_file(File::new_gitignore(&["!keep.log"]))
+            .add_file(File::new("keep.log", b"kept".to_vec()))
+            .add_file(File::new_empty("debug.log"))
+            .add_dir(Dir::new("empty")))
+        .add_dir(Dir::new(".git")
+            .add_file(File::new("HEAD", b"ref: refs/heads/main".to_vec()))
+            .add_dir(Dir::new("hooks")
+                .add_file(File::new_empty("pre-commit"))));
+
+    let fs = TmpFilesystem::new(&fs_template);
+    let root = fs.path().join("dir");
+    let dest = fs.path().join("dist");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::set_permissions(root.join("run.sh"), std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::os::unix::fs::symlink("../run.sh", root.join("src/link")).unwrap();
+    }
+
+    copy_tree(&root, ".gitignore", None, &dest).unwrap();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        assert_eq!(std::fs::metadata(dest.join("run.sh")).unwrap().permissions().mode() & 0o777, 0o755);
+        assert_eq!(std::fs::read_link(dest.join("src/link")).unwrap(), Path::new("../run.sh"));
+        std::fs::remove_file(dest.join("src/link")).unwrap();
+    }
+
+    let expected_template = Dir::new("dist")
+        .add_file(File::new_gitignore(&["*.log", "build/"]))
+        .add_file(File::new("run.sh", b"#!/bin/sh".to_vec()))
+        .add_dir(Dir::new("src")
+            .add_file(File::new_gitignore(&["!keep.log"]))
+            .add_file(File::new("keep.log", b"kept".to_vec()))
+            .add_dir(Dir::new("empty")));
+
+    // the repository isn't copied
+    assert!(Checker::check_dir(fs.path(), &expected_template));
+    assert!(dest.join(".git").exists() == false);
+    assert_eq!(std::fs::read(dest.join("src/keep.log")).unwrap(), b"kept");
+
+    // a second copy would mix both trees
+    assert!(copy_tree(&root, ".gitignore", None, &dest).is_err());
+}
+
+
+// writes the included entries to a tar, tar.gz or zip archive, with paths relative to the root
+pub fn pack_tree(root: &Path, ignore_file_name: &str, root_rules: Option<&RootRules>, output: &Path) -> anyhow::Result<()> {
+
+    let format = ArchiveFormat::from_path(output).ok_or_else(|| anyhow::anyhow!("unknown archive format, expected .tar, .tar.gz, .tgz or .zip, path: {}", output.display()))?;
+    let entries = included_entries(root, ignore_file_name, root_rules, output);
+
+    let file = std::io::BufWriter::new(std::fs::File::create(output)?);
+
+    match format {
+
+        ArchiveFormat::Tar => write_tar(root, &entries, file)?.flush()?,
+        ArchiveFormat::TarGz => {
+
+            let encoder = write_tar(root, &entries, flate2::write::GzEncoder::new(file, flate2::Compression::default()))?;
+            encoder.finish()?.flush()?;
+        },
+        ArchiveFormat::Zip => write_zip(root, &entries, file)?.flush()?
+    }
+
+    return Ok(());
+}
+
+fn write_tar<W: Write>(root: &Path, entries: &[ListedEntry], writer: W) -> anyhow::Result<W> {
+
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+
+    for entry in entries {
+
+        let path = relative_path(root, entry);
+
+        match entry.is_dir {
+
+            true => builder.append_dir(path, &entry.path)?,
+            false => builder.append_path_with_name(&entry.path, path)?
+        }
+    }
+
+    return Ok(builder.into_inner()?);
+}
+
+fn write_zip<W: Write + std::io::Seek>(root: &Path, entries: &[ListedEntry], writer: W) -> anyhow::Result<W> {
+
+    let mut writer = zip::ZipWriter::new(writer);
+
+    for entry in entries {
+
+        let path = relative_path(root, entry);
+        let name = zip_entry_name(path).ok_or_else(|| anyhow::anyhow!("zip entries must be valid UTF-8, path: {}", entry.path.display()))?;
+
+        let metadata = std::fs::symlink_metadata(&entry.path)?;
+        let options = zip::write::SimpleFileOptions::default().unix_permissions(unix_mode(&metadata));
+
+        if metadata.is_dir() {
+
+            writer.add_directory(name, options)?;
+        }
+        else if metadata.is_symlink() {
+
+            let target = std::fs::read_link(&entry.path)?;
+            let target = target.to_str().ok_or_else(|| anyhow::anyhow!("zip symlink targets must be valid UTF-8, path: {}", entry.path.display()))?;
+
+            writer.add_symlink(name, target, options)?;
+        }
+        else {
+
+            writer.start_file(name, options)?;
+            std::io::copy(&mut std::fs::File::open(&entry.path)?, &mut writer)?;
+        }
+    }
+
+    return Ok(writer.finish()?);
+}
+
+// zip paths always use "/"
+fn zip_entry_name(path: &Path) -> Option<String> {
+
+    let components = path.components().map(|component| component.as_os_str().to_str()).collect::<Option<Vec<_>>>()?;
+
+    return Some(components.join("/"));
+}
+
+#[cfg(unix)]
+fn unix_mode(metadata: &std::fs::Metadata) -> u32 {
+
+    use std::os::unix::fs::PermissionsExt;
+
+    return metadata.permissions().mode() & 0o7777;
+}
+
+#[cfg(not(unix))]
+fn unix_mode(metadata: &std::fs::Metadata) -> u32 {
+
+    return if metadata.permissions().readonly() { 0o444 } else { 0o644 };
+}
+
+#[test]
+fn test_pack_tree() {
+
+    use std::path::PathBuf;
+    use crate::file_system::{EntryType, FileSystem};
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["*.log", "build/", "dist.*"]))
+        .add_file(File::new("main.log", b"log".to_vec()))
+        .add_file(File::new("main.rs", b"fn main() {}".to_vec()))
+        .add_dir(Dir::new("build")
+            .add_file(File::new_gitignore(&["hidden"])))
+        .add_dir(Dir::new("src")
+            .add_file(File::new_gitignore(&["!keep.log"]))
+            .add_file(File::new("keep.log", b"kept".to_vec()))
+            .add_file(File::new_empty("debug.log")))
+        .add_dir(Dir::new(".git")
+            .add_file(File::new("config", b"[core]".to_vec())));
+
+    let fs = TmpFilesystem::new(&fs_template);
+    let root = fs.path().join("dir");
+
+    // packed archives, without the repository, can be flattened directly, the excluded ignore files are missing from them
+    for name in ["dist.tar", "dist.tar.gz", "dist.zip"] {
+
+        let output = root.join(name);
+        pack_tree(&root, ".gitignore", None, &output).unwrap();
+
+        let format = ArchiveFormat::from_path(&output).unwrap();
+        let archive_fs = crate::archive::load_archive(&output, format, Path::new("/mnt"), ".gitignore").unwrap();
+
+        assert_eq!(archive_fs.read_dir(Path::new("/mnt")).unwrap(), [
+            (PathBuf::from("/mnt/.gitignore"), EntryType::File),
+            (PathBuf::from("/mnt/main.rs"), EntryType::File),
+            (PathBuf::from("/mnt/src"), EntryType::Dir)
+        ], "{}", name);
+
+        assert_eq!(archive_fs.read_dir(Path::new("/mnt/src")).unwrap(), [
+            (PathBuf::from("/mnt/src/.gitignore"), EntryType::File),
+            (PathBuf::from("/mnt/src/keep.log"), EntryType::File)
+        ], "{}", name);
+    }
+
+    assert!(pack_tree(&root, ".gitignore", None, &root.join("dist.rar")).is_err());
+}
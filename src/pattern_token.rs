@@ -0,0 +1,175 @@
+// reference: https://github.com/git/git/blob/4b79ee4b0cd1130ba8907029cdc5f6a1632aca26/wildmatch.c
+
+
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PatternToken<'a> {
+
+    Literal(char),
+
+    // character preceded by a backslash
+    Escaped(char),
+    Slash,
+    Question,
+
+    // run of asterisks that isn't a whole path component, it behaves like a single "*"
+    Star(&'a str),
+
+    // run of two or more asterisks delimited by slashes or by the pattern boundaries
+    DoubleStar,
+
+    // bracket expression, brackets included
+    Class(&'a str)
+}
+
+
+pub fn tokenize_pattern(pattern: &str) -> Vec<PatternToken<'_>> {
+
+    let bytes = pattern.as_bytes();
+
+    let mut tokens = Vec::new();
+    let mut chars = pattern.char_indices().peekable();
+
+    while let Some((idx, ch)) = chars.next() {
+
+        let token = match ch {
+
+            '\\' => match chars.next() {
+
+                Some((_, escaped_ch)) => PatternToken::Escaped(escaped_ch),
+
+                // a trailing backslash never matches in git, it's kept as written
+                None => PatternToken::Literal('\\')
+            },
+            '/' => PatternToken::Slash,
+            '?' => PatternToken::Question,
+            '*' => {
+
+                let mut end = idx + 1;
+
+                while chars.next_if(|(_, ch)| *ch == '*').is_some() {
+
+                    end += 1;
+                }
+
+                let delimited_start = idx == 0 || bytes[idx - 1] == b'/';
+                let delimited_end = end == bytes.len() || bytes[end] == b'/';
+
+                if end - idx > 1 && delimited_start && delimited_end {
+
+                    PatternToken::DoubleStar
+                }
+                else {
+
+                    PatternToken::Star(&pattern[idx..end])
+                }
+            },
+            '[' => match find_class_end(bytes, idx) {
+
+                Some(end) => {
+
+                    while chars.next_if(|(char_idx, _)| *char_idx < end).is_some() {}
+                    PatternToken::Class(&pattern[idx..end])
+                },
+
+                // unterminated bracket expressions are taken literally
+                None => PatternToken::Literal('[')
+            },
+            _ => PatternToken::Literal(ch)
+        };
+
+        tokens.push(token);
+    }
+
+    return tokens;
+}
+
+#[test]
+fn test_tokenize_pattern() {
+
+    use PatternToken::*;
+
+    #[track_caller]
+    fn assert_tokens(pattern: &str, expected: &[PatternToken]) {
+
+        assert_eq!(tokenize_pattern(pattern), expected);
+    }
+
+    assert_tokens("", &[]);
+    assert_tokens("ab", &[Literal('a'), Literal('b')]);
+    assert_tokens("a/b", &[Literal('a'), Slash, Literal('b')]);
+    assert_tokens("a?", &[Literal('a'), Question]);
+
+    // escapes
+    assert_tokens("\\#a", &[Escaped('#'), Literal('a')]);
+    assert_tokens("\\!a", &[Escaped('!'), Literal('a')]);
+    assert_tokens("a\\ ", &[Literal('a'), Escaped(' ')]);
+    assert_tokens("\\*", &[Escaped('*')]);
+    assert_tokens("\\\\", &[Escaped('\\')]);
+    assert_tokens("a\\", &[Literal('a'), Literal('\\')]);
+
+    // asterisks
+    assert_tokens("*", &[Star("*")]);
+    assert_tokens("*.log", &[Star("*"), Literal('.'), Literal('l'), Literal('o'), Literal('g')]);
+    assert_tokens("**", &[DoubleStar]);
+    assert_tokens("**/a", &[DoubleStar, Slash, Literal('a')]);
+    assert_tokens("a/**", &[Literal('a'), Slash, DoubleStar]);
+    assert_tokens("a/**/b", &[Literal('a'), Slash, DoubleStar, Slash, Literal('b')]);
+    assert_tokens("a/***/b", &[Literal('a'), Slash, DoubleStar, Slash, Literal('b')]);
+    assert_tokens("a**", &[Literal('a'), Star("**")]);
+    assert_tokens("**a", &[Star("**"), Literal('a')]);
+    assert_tokens("a/**b", &[Literal('a'), Slash, Star("**"), Literal('b')]);
+    assert_tokens("\\**", &[Escaped('*'), Star("*")]);
+
+    // bracket expressions
+    assert_tokens("[ab]c", &[Class("[ab]"), Literal('c')]);
+    assert_tokens("[!ab]", &[Class("[!ab]")]);
+    assert_tokens("[^ab]", &[Class("[^ab]")]);
+    assert_tokens("[]a]", &[Class("[]a]")]);
+    assert_tokens("[!]a]", &[Class("[!]a]")]);
+    assert_tokens("[a\\]]", &[Class("[a\\]]")]);
+    assert_tokens("[[:alpha:]]", &[Class("[[:alpha:]]")]);
+    assert_tokens("[é]", &[Class("[é]")]);
+    assert_tokens("[ab", &[Literal('['), Literal('a'), Literal('b')]);
+}
+
+
+// returns the index right after the closing bracket
+fn find_class_end(bytes: &[u8], start: usize) -> Option<usize> {
+
+    assert_eq!(bytes[start], b'[');
+
+    let mut idx = start + 1;
+
+    if idx < bytes.len() && (bytes[idx] == b'!' || bytes[idx] == b'^') {
+
+        idx += 1;
+    }
+
+    // a closing bracket right at the start is a member of the class
+    if idx < bytes.len() && bytes[idx] == b']' {
+
+        idx += 1;
+    }
+
+    while idx < bytes.len() {
+
+        match bytes[idx] {
+
+            b'\\' => idx += 2,
+            b'[' if bytes.get(idx + 1) == Some(&b':') => {
+
+                // character class name, like "[:alpha:]"
+                match bytes[idx + 2..].windows(2).position(|window| window == b":]") {
+
+                    Some(position) => idx += 2 + position + 2,
+                    None => idx += 1
+                }
+            },
+            b']' => return Some(idx + 1),
+            _ => idx += 1
+        }
+    }
+
+    return None;
+}
@@ -0,0 +1,142 @@
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use crate::file_system::{EntryStamp, EntryType, FileSystem};
+
+
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StatsFormat {
+
+    Text,
+    Json
+}
+
+
+// cost of a traversal, "--files-from" and "--tracked-only" only visit the directories of the listed files, without a job stack
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FlattenStats {
+
+    pub visited_dirs: u64,
+
+    // subdirectories excluded by the ignore files, their subtrees aren't read
+    pub pruned_dirs: u64,
+
+    pub ignore_files: u64,
+
+    // comments and blank lines kept by "--keep-comments" aren't counted
+    pub patterns: u64,
+
+    // ignore files in the longest "IgnoreNode" chain
+    pub max_chain_depth: u64,
+
+    pub peak_job_stack: u64,
+    pub io_time: Duration,
+
+    // everything but the IO: decoding, pattern building and matching
+    pub matching_time: Duration
+}
+
+impl FlattenStats {
+
+    // "chain_depth" is the one of the context extended with the file
+    pub fn add_ignore_file(&mut self, patterns: &[std::ffi::OsString], chain_depth: usize) {
+
+        self.ignore_files += 1;
+        self.patterns += patterns.iter().filter(|pattern| is_comment_line(pattern) == false).count() as u64;
+        self.max_chain_depth = self.max_chain_depth.max(chain_depth as u64);
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+
+        return serde_json::json!({
+            "visited_dirs": self.visited_dirs,
+            "pruned_dirs": self.pruned_dirs,
+            "ignore_files": self.ignore_files,
+            "patterns": self.patterns,
+            "max_chain_depth": self.max_chain_depth,
+            "peak_job_stack": self.peak_job_stack,
+            "io_seconds": self.io_time.as_secs_f64(),
+            "matching_seconds": self.matching_time.as_secs_f64()
+        });
+    }
+}
+
+impl std::fmt::Display for FlattenStats {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        writeln!(f, "directories visited: {}", self.visited_dirs)?;
+        writeln!(f, "directories pruned: {}", self.pruned_dirs)?;
+        writeln!(f, "ignore files read: {}", self.ignore_files)?;
+        writeln!(f, "patterns: {}", self.patterns)?;
+        writeln!(f, "max ignore chain depth: {}", self.max_chain_depth)?;
+        writeln!(f, "peak job stack size: {}", self.peak_job_stack)?;
+        writeln!(f, "IO time: {:?}", self.io_time)?;
+        write!(f, "matching time: {:?}", self.matching_time)?;
+
+        return Ok(());
+    }
+}
+
+
+// blank lines and comments, as kept by "--keep-comments"
+fn is_comment_line(pattern: &std::ffi::OsStr) -> bool {
+
+    let bytes = pattern.as_encoded_bytes();
+
+    return bytes.is_empty() || bytes.starts_with(b"#");
+}
+
+
+// times the calls of another file system
+pub struct StatsFileSystem<'a> {
+
+    inner: &'a dyn FileSystem,
+    pub io_time: Cell<Duration>
+}
+
+impl<'a> StatsFileSystem<'a> {
+
+    pub fn new(inner: &'a dyn FileSystem) -> Self {
+
+        return Self{inner, io_time: Cell::new(Duration::ZERO)};
+    }
+
+    fn timed<T>(&self, call: impl FnOnce() -> T) -> T {
+
+        let started = Instant::now();
+        let result = call();
+        self.io_time.set(self.io_time.get() + started.elapsed());
+
+        return result;
+    }
+}
+
+impl FileSystem for StatsFileSystem<'_> {
+
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<(PathBuf, EntryType)>> {
+
+        return self.timed(|| self.inner.read_dir(path));
+    }
+
+    fn read_file(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+
+        return self.timed(|| self.inner.read_file(path));
+    }
+
+    fn entry_type(&self, path: &Path) -> Option<EntryType> {
+
+        return self.timed(|| self.inner.entry_type(path));
+    }
+
+    fn stamp(&self, path: &Path) -> Option<EntryStamp> {
+
+        return self.timed(|| self.inner.stamp(path));
+    }
+
+    fn file_size(&self, path: &Path) -> Option<u64> {
+
+        return self.timed(|| self.inner.file_size(path));
+    }
+}
@@ -0,0 +1,531 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::file_system::OsFileSystem;
+use crate::ignore_node::IgnoreNode;
+use crate::job::{self, Job, OutputOrder, RootRules, VisitedDir};
+
+
+
+struct TreeDir {
+
+    // context inherited from the parent directory, kept to rebuild this subtree alone
+    parent_context: Option<Arc<IgnoreNode>>,
+    ignore_context: Option<Arc<IgnoreNode>>,
+    patterns: Option<Vec<OsString>>,
+    child_dirs: Vec<PathBuf>
+}
+
+// flattening state of every visited directory, updated one subtree at a time
+pub struct FlattenTree {
+
+    root: PathBuf,
+    ignore_file_name: String,
+    keep_comments: bool,
+    root_sections: Vec<Vec<OsString>>,
+    dirs: HashMap<PathBuf, TreeDir>
+}
+
+impl FlattenTree {
+
+    // "before_read" is called on each directory before it's read, so a watch set there misses none of its changes
+    // the root rules don't change while watching, their context is kept as the root's parent context
+    pub fn new(root: &Path, ignore_file_name: &str, keep_comments: bool, root_rules: Option<&RootRules>, before_read: &mut dyn FnMut(&Path)) -> Self {
+
+        let root_sections = root_rules.map_or_else(Vec::new, |rules| rules.sections().to_vec());
+
+        let mut tree = Self{root: root.to_owned(), ignore_file_name: ignore_file_name.to_owned(), keep_comments, root_sections, dirs: HashMap::new()};
+        tree.build_subtree(root, root_rules.and_then(RootRules::context), before_read);
+
+        return tree;
+    }
+
+    pub fn patterns(&self, order: OutputOrder) -> Vec<OsString> {
+
+        // replays the job stack of "job::flatten", so the traversal order is the same
+        let mut sections = self.root_sections.iter().map(|section| (self.root.clone(), section.clone())).collect::<Vec<_>>();
+        let mut stack = vec![self.root.as_path()];
+
+        while let Some(path) = stack.pop() {
+
+            let dir = &self.dirs[path];
+
+            if let Some(ref patterns) = dir.patterns {
+
+                sections.push((path.to_owned(), patterns.clone()));
+            }
+
+            stack.extend(dir.child_dirs.iter().map(PathBuf::as_path));
+        }
+
+        job::order_sections(&self.root, &mut sections, order);
+
+        return job::join_sections(sections, self.keep_comments);
+    }
+
+    // returns the added directories
+    fn build_subtree(&mut self, path: &Path, parent_context: Option<Arc<IgnoreNode>>, before_read: &mut dyn FnMut(&Path)) -> Vec<PathBuf> {
+
+        let mut added_dirs = Vec::new();
+        let job = Job{path: path.to_owned(), ignore_context: parent_context};
+
+        let process = &mut |job: Job| {
+
+            before_read(&job.path);
+            return job::process_job(&OsFileSystem, &self.root, job, &self.ignore_file_name, self.keep_comments);
+        };
+
+        job::walk(job, process, &mut |dir| {
+
+            let VisitedDir{path, parent_context, ignore_context, patterns, child_dirs, ..} = dir;
+
+            added_dirs.push(path.clone());
+            self.dirs.insert(path, TreeDir{parent_context, ignore_context, patterns, child_dirs});
+        });
+
+        return added_dirs;
+    }
+
+    // returns the removed directories
+    fn remove_subtree(&mut self, path: &Path) -> Vec<PathBuf> {
+
+        let mut removed_dirs = Vec::new();
+        let mut stack = vec![path.to_owned()];
+
+        while let Some(path) = stack.pop() {
+
+            if let Some(dir) = self.dirs.remove(&path) {
+
+                stack.extend(dir.child_dirs);
+                removed_dirs.push(path);
+            }
+        }
+
+        return removed_dirs;
+    }
+
+    // reloads the directory's ignore file and everything below it, the ancestors' ignore nodes are reused
+    pub fn rebuild_subtree(&mut self, path: &Path, before_read: &mut dyn FnMut(&Path)) -> TreeUpdate {
+
+        let Some(dir) = self.dirs.get(path) else {
+
+            return TreeUpdate::default();
+        };
+
+        let parent_context = dir.parent_context.clone();
+        let removed_dirs = self.remove_subtree(path);
+
+        // the directory itself was removed, its parent's update will drop it
+        if path.is_dir() == false {
+
+            return TreeUpdate{added_dirs: Vec::new(), removed_dirs};
+        }
+
+        let added_dirs = self.build_subtree(path, parent_context, before_read);
+
+        return TreeUpdate{added_dirs, removed_dirs};
+    }
+
+    pub fn add_child_dir(&mut self, parent: &Path, child: &Path, before_read: &mut dyn FnMut(&Path)) -> TreeUpdate {
+
+        if self.dirs.contains_key(child) || child.is_dir() == false {
+
+            return TreeUpdate::default();
+        }
+
+        let Some(parent_dir) = self.dirs.get_mut(parent) else {
+
+            return TreeUpdate::default();
+        };
+
+        let excluded = job::is_pruned(&parent_dir.ignore_context, child);
+
+        if excluded {
+
+            return TreeUpdate::default();
+        }
+
+        // same order as "FileSystem::read_dir"
+        let position = parent_dir.child_dirs.partition_point(|dir| dir.as_path() < child);
+        parent_dir.child_dirs.insert(position, child.to_owned());
+
+        let ignore_context = parent_dir.ignore_context.clone();
+        let added_dirs = self.build_subtree(child, ignore_context, before_read);
+
+        return TreeUpdate{added_dirs, removed_dirs: Vec::new()};
+    }
+
+    pub fn remove_child_dir(&mut self, parent: &Path, child: &Path) -> TreeUpdate {
+
+        if let Some(parent_dir) = self.dirs.get_mut(parent) {
+
+            parent_dir.child_dirs.retain(|dir| dir != child);
+        }
+
+        return TreeUpdate{added_dirs: Vec::new(), removed_dirs: self.remove_subtree(child)};
+    }
+}
+
+
+#[derive(Debug, Default)]
+pub struct TreeUpdate {
+
+    pub added_dirs: Vec<PathBuf>,
+    pub removed_dirs: Vec<PathBuf>
+}
+
+impl TreeUpdate {
+
+    pub fn is_empty(&self) -> bool {
+
+        return self.added_dirs.is_empty() && self.removed_dirs.is_empty();
+    }
+}
+
+
+#[test]
+fn test_flatten_tree() {
+
+    use crate::filesystem::tmp_filesystem::TmpFilesystem;
+    use crate::filesystem::template::{Dir, File};
+
+
+    let fs_template = Dir::new("dir")
+        .add_file(File::new_gitignore(&["build/"]))
+        .add_dir(Dir::new("a")
+            .add_file(File::new_gitignore(&["*.log"]))
+            .add_dir(Dir::new("b")))
+        .add_dir(Dir::new("build")
+            .add_file(File::new_gitignore(&["hidden"])))
+        .add_dir(Dir::new("c"));
+
+    let fs = TmpFilesystem::new(&fs_template);
+    let root = fs.path().join("dir");
+
+    #[track_caller]
+    fn assert_matches_flatten(tree: &FlattenTree, root: &Path) {
+
+        for order in [OutputOrder::Traversal, OutputOrder::Path, OutputOrder::DepthFirst, OutputOrder::BreadthFirst] {
+
+            assert_eq!(tree.patterns(order), job::flatten(&OsFileSystem, root, ".gitignore", job::FlattenOptions{order, ..Default::default()}));
+        }
+    }
+
+    let mut read_dirs = Vec::new();
+    let mut tree = FlattenTree::new(&root, ".gitignore", false, None, &mut |dir| read_dirs.push(dir.to_owned()));
+    read_dirs.sort();
+
+    assert_eq!(read_dirs, [root.clone(), root.join("a"), root.join("a/b"), root.join("c")]);
+    assert_matches_flatten(&tree, &root);
+    assert!(tree.dirs.contains_key(&root.join("build")) == false);
+
+    let root_context = tree.dirs[&root].ignore_context.clone().unwrap();
+
+    // changed ignore file, only its subtree is rebuilt
+    std::fs::write(root.join("a/b/.gitignore"), "*.tmp\n").unwrap();
+    let update = tree.rebuild_subtree(&root.join("a/b"), &mut |_| {});
+    assert_eq!(update.added_dirs, [root.join("a/b")]);
+    assert_eq!(update.removed_dirs, [root.join("a/b")]);
+    assert_matches_flatten(&tree, &root);
+    assert!(Arc::ptr_eq(tree.dirs[&root].ignore_context.as_ref().unwrap(), &root_context));
+
+    // a previously excluded directory becomes visible
+    std::fs::write(root.join(".gitignore"), "!build/\n").unwrap();
+    tree.rebuild_subtree(&root, &mut |_| {});
+    assert_matches_flatten(&tree, &root);
+    assert!(tree.dirs.contains_key(&root.join("build")));
+
+    // added and removed directories
+    std::fs::create_dir_all(root.join("c/d")).unwrap();
+    std::fs::write(root.join("c/d/.gitignore"), "foo\n").unwrap();
+    let update = tree.add_child_dir(&root.join("c"), &root.join("c/d"), &mut |_| {});
+    assert_eq!(update.added_dirs, [root.join("c/d")]);
+    assert_matches_flatten(&tree, &root);
+
+    std::fs::remove_dir_all(root.join("a")).unwrap();
+    let update = tree.remove_child_dir(&root, &root.join("a"));
+    assert_eq!(update.removed_dirs.len(), 2);
+    assert_matches_flatten(&tree, &root);
+
+    // the root rules are kept across the rebuilds
+    let mut rules = RootRules::default();
+    rules.add_patterns(&root, Path::new("/flf.toml"), &["*.swp".to_owned()], true);
+    rules.add_excluded_dirs(&root, Path::new("/flf.toml"), &["c".to_owned()]);
+
+    let mut tree = FlattenTree::new(&root, ".gitignore", true, Some(&rules), &mut |_| {});
+    tree.rebuild_subtree(&root, &mut |_| {});
+
+    assert_eq!(tree.patterns(OutputOrder::DepthFirst), job::flatten(&OsFileSystem, &root, ".gitignore", job::FlattenOptions{keep_comments: true, root_rules: Some(&rules), ..Default::default()}));
+    assert!(tree.dirs.contains_key(&root.join("c")) == false);
+}
+
+
+#[cfg(target_os = "linux")]
+pub use inotify_watcher::Watcher;
+
+#[cfg(target_os = "linux")]
+mod inotify_watcher {
+
+    use std::collections::{HashMap, HashSet};
+    use std::ffi::OsStr;
+    use std::path::{Path, PathBuf};
+    use inotify::{Event, EventMask, Inotify, WatchDescriptor, WatchMask, Watches};
+    use super::{FlattenTree, RootRules, TreeUpdate};
+
+
+    enum DirChange {
+
+        IgnoreFile,
+        DirAdded(PathBuf),
+        DirRemoved(PathBuf),
+        Overflow
+    }
+
+    // watches every directory of the tree, added before the directory is read
+    struct DirWatches {
+
+        watches: Watches,
+        dirs: HashMap<WatchDescriptor, PathBuf>,
+        descriptors: HashMap<PathBuf, WatchDescriptor>,
+
+        // the tree's callbacks can't fail, the first error is kept for the caller
+        error: Option<std::io::Error>
+    }
+
+    impl DirWatches {
+
+        fn add(&mut self, dir: &Path) {
+
+            let mask = WatchMask::CREATE | WatchMask::DELETE | WatchMask::MOVE | WatchMask::CLOSE_WRITE | WatchMask::ONLYDIR;
+
+            // a watched directory keeps its descriptor, a new one replaces a deleted directory's
+            match self.watches.add(dir, mask) {
+
+                Ok(descriptor) => {
+
+                    if let Some(previous) = self.descriptors.insert(dir.to_owned(), descriptor.clone()) {
+
+                        self.dirs.remove(&previous);
+                    }
+
+                    self.dirs.insert(descriptor, dir.to_owned());
+                },
+                Err(err) => { self.error.get_or_insert(err); }
+            }
+        }
+
+        fn remove(&mut self, dir: &Path) {
+
+            if let Some(descriptor) = self.descriptors.remove(dir) {
+
+                self.dirs.remove(&descriptor);
+
+                // fails when the directory was deleted, the kernel already dropped the watch
+                let _ = self.watches.remove(descriptor);
+            }
+        }
+
+        fn take_error(&mut self) -> std::io::Result<()> {
+
+            return match self.error.take() {
+
+                Some(err) => Err(err),
+                None => Ok(())
+            };
+        }
+    }
+
+
+    pub struct Watcher {
+
+        inotify: Inotify,
+        tree: FlattenTree,
+        dir_watches: DirWatches
+    }
+
+    impl Watcher {
+
+        pub fn new(root: &Path, ignore_file_name: &str, keep_comments: bool, root_rules: Option<&RootRules>) -> std::io::Result<Self> {
+
+            let inotify = Inotify::init()?;
+            let mut dir_watches = DirWatches{watches: inotify.watches(), dirs: HashMap::new(), descriptors: HashMap::new(), error: None};
+
+            let tree = FlattenTree::new(root, ignore_file_name, keep_comments, root_rules, &mut |dir| dir_watches.add(dir));
+            dir_watches.take_error()?;
+
+            return Ok(Self{inotify, tree, dir_watches});
+        }
+
+        pub fn tree(&self) -> &FlattenTree {
+
+            return &self.tree;
+        }
+
+        // blocks until the next batch of events, returns whether the tree changed
+        pub fn wait_for_changes(&mut self) -> std::io::Result<bool> {
+
+            let mut buffer = [0; 16384];
+            let events = self.inotify.read_events_blocking(&mut buffer)?;
+
+            return self.apply_events(events);
+        }
+
+        fn apply_events<'a>(&mut self, events: impl Iterator<Item = Event<&'a OsStr>>) -> std::io::Result<bool> {
+
+            let mut changes = Vec::new();
+
+            for event in events {
+
+                if event.mask.contains(EventMask::Q_OVERFLOW) {
+
+                    changes.push((PathBuf::new(), DirChange::Overflow));
+                    continue;
+                }
+
+                let (Some(dir), Some(name)) = (self.dir_watches.dirs.get(&event.wd), event.name) else {
+
+                    continue;
+                };
+
+                let path = dir.join(name);
+                let is_dir = event.mask.contains(EventMask::ISDIR);
+
+                let change = if name == self.tree.ignore_file_name.as_str() {
+
+                    DirChange::IgnoreFile
+                }
+                else if is_dir && event.mask.intersects(EventMask::CREATE | EventMask::MOVED_TO) {
+
+                    DirChange::DirAdded(path)
+                }
+                else if is_dir && event.mask.intersects(EventMask::DELETE | EventMask::MOVED_FROM) {
+
+                    DirChange::DirRemoved(path)
+                }
+                else {
+
+                    continue;
+                };
+
+                changes.push((dir.clone(), change));
+            }
+
+            let mut update = TreeUpdate::default();
+            let mut rebuilt_dirs = HashSet::<PathBuf>::new();
+            let before_read = &mut |dir: &Path| self.dir_watches.add(dir);
+
+            for (dir, change) in changes {
+
+                // a rebuilt subtree already reflects every later change below it
+                if rebuilt_dirs.iter().any(|rebuilt_dir| dir.starts_with(rebuilt_dir)) {
+
+                    continue;
+                }
+
+                let change_update = match change {
+
+                    DirChange::IgnoreFile => {
+
+                        rebuilt_dirs.insert(dir.clone());
+                        self.tree.rebuild_subtree(&dir, before_read)
+                    },
+                    DirChange::DirAdded(path) => self.tree.add_child_dir(&dir, &path, before_read),
+                    DirChange::DirRemoved(path) => self.tree.remove_child_dir(&dir, &path),
+                    DirChange::Overflow => {
+
+                        let root = self.tree.root.clone();
+                        rebuilt_dirs.insert(root.clone());
+                        self.tree.rebuild_subtree(&root, before_read)
+                    }
+                };
+
+                update.added_dirs.extend(change_update.added_dirs);
+                update.removed_dirs.extend(change_update.removed_dirs);
+            }
+
+            self.dir_watches.take_error()?;
+
+            // rebuilt directories keep their watch
+            let added_dirs = update.added_dirs.iter().collect::<HashSet<_>>();
+
+            for dir in update.removed_dirs.iter().filter(|dir| added_dirs.contains(dir) == false) {
+
+                self.dir_watches.remove(dir);
+            }
+
+            return Ok(update.is_empty() == false);
+        }
+    }
+
+    #[test]
+    fn test_watcher() {
+
+        use crate::filesystem::tmp_filesystem::TmpFilesystem;
+        use crate::filesystem::template::{Dir, File};
+        use crate::job::{self, OutputOrder};
+
+
+        let fs_template = Dir::new("dir")
+            .add_file(File::new_gitignore(&["foo"]))
+            .add_dir(Dir::new("a"));
+
+        let fs = TmpFilesystem::new(&fs_template);
+        let root = fs.path().join("dir");
+
+        let mut watcher = Watcher::new(&root, ".gitignore", false, None).unwrap();
+
+        // a missed event fails the test instead of blocking it
+        let wait_for_changes = |watcher: &mut Watcher| {
+
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+            let mut buffer = [0; 16384];
+
+            loop {
+
+                match watcher.inotify.read_events(&mut buffer) {
+
+                    Ok(events) => {
+
+                        let mut events = events.peekable();
+
+                        if events.peek().is_some() {
+
+                            return watcher.apply_events(events).unwrap();
+                        }
+                    },
+                    Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {},
+                    Err(err) => panic!("{}", err)
+                }
+
+                assert!(std::time::Instant::now() < deadline, "no event before the deadline");
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+        };
+        let flatten = || job::flatten(&crate::file_system::OsFileSystem, &root, ".gitignore", job::FlattenOptions::default());
+
+        // new ignore file
+        std::fs::write(root.join("a/.gitignore"), "bar\n").unwrap();
+        assert!(wait_for_changes(&mut watcher));
+        assert_eq!(watcher.tree().patterns(OutputOrder::DepthFirst), flatten());
+
+        // new directory, then an ignore file inside it
+        std::fs::create_dir(root.join("a/b")).unwrap();
+        assert!(wait_for_changes(&mut watcher));
+
+        std::fs::write(root.join("a/b/.gitignore"), "baz\n").unwrap();
+        assert!(wait_for_changes(&mut watcher));
+        assert_eq!(watcher.tree().patterns(OutputOrder::DepthFirst), flatten());
+
+        // unrelated file
+        std::fs::write(root.join("a/file"), "").unwrap();
+        assert!(wait_for_changes(&mut watcher) == false);
+
+        // moved directory
+        std::fs::rename(root.join("a"), root.join("c")).unwrap();
+        assert!(wait_for_changes(&mut watcher));
+        assert_eq!(watcher.tree().patterns(OutputOrder::DepthFirst), flatten());
+        assert!(watcher.tree().dirs.contains_key(&root.join("c/b")));
+        assert!(watcher.tree().dirs.contains_key(&root.join("a")) == false);
+    }
+}